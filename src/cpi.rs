@@ -0,0 +1,369 @@
+//! Helpers for programs that call the calculator through cross-program
+//! invocation
+//!
+//! Depend on this crate with the `no-entrypoint` feature to use them.
+
+use crate::{
+    error::CalculatorError,
+    instruction::{
+        self, CalculatorInstruction, MulDivOperands, Operands, Operation, OperationResult,
+    },
+    math::Rounding,
+};
+use solana_program::{
+    account_info::AccountInfo, msg, program::invoke_signed, program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+/// A calculator account together with the authority allowed to modify it
+#[derive(Clone, Copy)]
+pub struct Calculator<'a, 'info> {
+    /// The calculator account
+    pub account: &'a AccountInfo<'info>,
+    /// The calculator account's authority, which must sign
+    pub authority: &'a AccountInfo<'info>,
+}
+
+/// Invoke an arithmetic instruction and decode the result it returns
+///
+/// With `calculator` set to `None` the result is only computed, not stored.
+/// `signer_seeds` lets a program-derived authority sign; pass `&[]` otherwise.
+pub fn invoke_operation(
+    program_id: &Pubkey,
+    calculator: Option<Calculator>,
+    instruction: &CalculatorInstruction,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<OperationResult, ProgramError> {
+    match calculator {
+        Some(calculator) => invoke_signed(
+            &instruction::operation(
+                program_id,
+                calculator.account.key,
+                calculator.authority.key,
+                instruction.clone(),
+            ),
+            &[calculator.account.clone(), calculator.authority.clone()],
+            signer_seeds,
+        )?,
+        None => invoke_signed(
+            &instruction::compute(program_id, instruction.clone()),
+            &[],
+            signer_seeds,
+        )?,
+    }
+
+    OperationResult::from_return_data(program_id).ok_or_else(|| {
+        msg!("Calculator did not return a result");
+        CalculatorError::InvalidReturnData.into()
+    })
+}
+
+/// Invoke `Add` and return `num1 + num2`
+pub fn add(
+    program_id: &Pubkey,
+    calculator: Option<Calculator>,
+    num1: u32,
+    num2: u32,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<u32, ProgramError> {
+    let instruction = CalculatorInstruction::Add { num1, num2 };
+    let result = invoke_operation(program_id, calculator, &instruction, signer_seeds)?;
+    unsigned(result)
+}
+
+/// Invoke `Subtract` and return `num1 - num2`
+pub fn subtract(
+    program_id: &Pubkey,
+    calculator: Option<Calculator>,
+    num1: u32,
+    num2: u32,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<u32, ProgramError> {
+    let instruction = CalculatorInstruction::Subtract { num1, num2 };
+    let result = invoke_operation(program_id, calculator, &instruction, signer_seeds)?;
+    unsigned(result)
+}
+
+/// Invoke `Multiply` and return `num1 * num2`
+pub fn multiply(
+    program_id: &Pubkey,
+    calculator: Option<Calculator>,
+    num1: u32,
+    num2: u32,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<u32, ProgramError> {
+    let instruction = CalculatorInstruction::Multiply { num1, num2 };
+    let result = invoke_operation(program_id, calculator, &instruction, signer_seeds)?;
+    unsigned(result)
+}
+
+/// Invoke `Divide` and return `num1 / num2`
+pub fn divide(
+    program_id: &Pubkey,
+    calculator: Option<Calculator>,
+    num1: u32,
+    num2: u32,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<u32, ProgramError> {
+    let instruction = CalculatorInstruction::Divide { num1, num2 };
+    let result = invoke_operation(program_id, calculator, &instruction, signer_seeds)?;
+    unsigned(result)
+}
+
+/// Invoke `Remainder` and return `num1 % num2`
+pub fn remainder(
+    program_id: &Pubkey,
+    calculator: Option<Calculator>,
+    num1: u32,
+    num2: u32,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<u32, ProgramError> {
+    let instruction = CalculatorInstruction::Remainder { num1, num2 };
+    let result = invoke_operation(program_id, calculator, &instruction, signer_seeds)?;
+    unsigned(result)
+}
+
+/// Invoke `SignedAdd` and return `num1 + num2`
+pub fn signed_add(
+    program_id: &Pubkey,
+    calculator: Option<Calculator>,
+    num1: i64,
+    num2: i64,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<i64, ProgramError> {
+    let instruction = CalculatorInstruction::SignedAdd { num1, num2 };
+    let result = invoke_operation(program_id, calculator, &instruction, signer_seeds)?;
+    signed(result)
+}
+
+/// Invoke `SignedSubtract` and return `num1 - num2`
+pub fn signed_subtract(
+    program_id: &Pubkey,
+    calculator: Option<Calculator>,
+    num1: i64,
+    num2: i64,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<i64, ProgramError> {
+    let instruction = CalculatorInstruction::SignedSubtract { num1, num2 };
+    let result = invoke_operation(program_id, calculator, &instruction, signer_seeds)?;
+    signed(result)
+}
+
+/// Invoke `Arithmetic` on `u64` operands and return `num1 <operation> num2`
+pub fn u64_operation(
+    program_id: &Pubkey,
+    calculator: Option<Calculator>,
+    operation: Operation,
+    num1: u64,
+    num2: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<u64, ProgramError> {
+    let instruction = CalculatorInstruction::Arithmetic {
+        operation,
+        operands: Operands::U64 { num1, num2 },
+    };
+    match invoke_operation(program_id, calculator, &instruction, signer_seeds)? {
+        OperationResult::U64(value) => Ok(value),
+        _ => Err(CalculatorError::InvalidReturnData.into()),
+    }
+}
+
+/// Invoke `Arithmetic` on `u128` operands and return `num1 <operation> num2`
+pub fn u128_operation(
+    program_id: &Pubkey,
+    calculator: Option<Calculator>,
+    operation: Operation,
+    num1: u128,
+    num2: u128,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<u128, ProgramError> {
+    let instruction = CalculatorInstruction::Arithmetic {
+        operation,
+        operands: Operands::U128 { num1, num2 },
+    };
+    match invoke_operation(program_id, calculator, &instruction, signer_seeds)? {
+        OperationResult::U128(value) => Ok(value),
+        _ => Err(CalculatorError::InvalidReturnData.into()),
+    }
+}
+
+/// Invoke `MulDiv` on `u64` operands and return `num1 * num2 / denominator`
+pub fn mul_div_u64(
+    program_id: &Pubkey,
+    calculator: Option<Calculator>,
+    num1: u64,
+    num2: u64,
+    denominator: u64,
+    rounding: Rounding,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<u64, ProgramError> {
+    let instruction = CalculatorInstruction::MulDiv {
+        operands: MulDivOperands::U64 {
+            num1,
+            num2,
+            denominator,
+        },
+        rounding,
+    };
+    match invoke_operation(program_id, calculator, &instruction, signer_seeds)? {
+        OperationResult::U64(value) => Ok(value),
+        _ => Err(CalculatorError::InvalidReturnData.into()),
+    }
+}
+
+/// Invoke `MulDiv` on `u128` operands and return `num1 * num2 / denominator`
+pub fn mul_div_u128(
+    program_id: &Pubkey,
+    calculator: Option<Calculator>,
+    num1: u128,
+    num2: u128,
+    denominator: u128,
+    rounding: Rounding,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<u128, ProgramError> {
+    let instruction = CalculatorInstruction::MulDiv {
+        operands: MulDivOperands::U128 {
+            num1,
+            num2,
+            denominator,
+        },
+        rounding,
+    };
+    match invoke_operation(program_id, calculator, &instruction, signer_seeds)? {
+        OperationResult::U128(value) => Ok(value),
+        _ => Err(CalculatorError::InvalidReturnData.into()),
+    }
+}
+
+/// Invoke `MemoryRecall` and return the calculator's memory register
+pub fn memory_recall(
+    program_id: &Pubkey,
+    calculator: Calculator,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<i64, ProgramError> {
+    let instruction = CalculatorInstruction::MemoryRecall;
+    let result = invoke_operation(program_id, Some(calculator), &instruction, signer_seeds)?;
+    signed(result)
+}
+
+fn unsigned(result: OperationResult) -> Result<u32, ProgramError> {
+    match result {
+        OperationResult::Unsigned(value) => Ok(value),
+        _ => Err(CalculatorError::InvalidReturnData.into()),
+    }
+}
+
+fn signed(result: OperationResult) -> Result<i64, ProgramError> {
+    match result {
+        OperationResult::Signed(value) => Ok(value),
+        _ => Err(CalculatorError::InvalidReturnData.into()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        state::CalcResult,
+        test_utils::{TestAccount, TestSyscallStubs, CALLER_PROGRAM_ID, CPI_PROGRAM_ID},
+    };
+    use solana_program::program_stubs;
+
+    #[test]
+    fn test_cpi_operations() {
+        program_stubs::set_syscall_stubs(Box::new(TestSyscallStubs));
+        let program_id = CPI_PROGRAM_ID;
+
+        // The caller program owns the authority as a program-derived address
+        let (authority_key, bump) =
+            Pubkey::find_program_address(&[b"authority"], &CALLER_PROGRAM_ID);
+        let signer_seeds: &[&[u8]] = &[b"authority", &[bump]];
+
+        let mut calc = TestAccount::new(CalcResult::LEN, &program_id);
+        CalcResult::new(authority_key)
+            .pack(calc.data_mut())
+            .unwrap();
+        let mut authority = TestAccount::new(0, &Pubkey::default());
+        authority.key = authority_key;
+        let calc_account = calc.info(false, true);
+        let authority_account = authority.info(false, false);
+        let calculator = Calculator {
+            account: &calc_account,
+            authority: &authority_account,
+        };
+
+        assert_eq!(
+            add(&program_id, Some(calculator), 100, 30, &[signer_seeds]),
+            Ok(130)
+        );
+        assert_eq!(
+            subtract(&program_id, Some(calculator), 100, 30, &[signer_seeds]),
+            Ok(70)
+        );
+        assert_eq!(
+            signed_subtract(&program_id, Some(calculator), 3, 5, &[signer_seeds]),
+            Ok(-2)
+        );
+        let state = CalcResult::unpack(&calc_account.data.borrow()).unwrap();
+        assert_eq!(state.add_result, 130);
+        assert_eq!(state.sub_result, 70);
+        assert_eq!(state.signed_sub_result, -2);
+        assert_eq!(
+            memory_recall(&program_id, calculator, &[signer_seeds]),
+            Ok(0)
+        );
+        assert_eq!(
+            u64_operation(
+                &program_id,
+                Some(calculator),
+                Operation::Multiply,
+                u32::MAX.into(),
+                2,
+                &[signer_seeds]
+            ),
+            Ok(u64::from(u32::MAX) * 2)
+        );
+        assert_eq!(
+            CalcResult::unpack(&calc_account.data.borrow())
+                .unwrap()
+                .u64_result,
+            u64::from(u32::MAX) * 2
+        );
+        assert_eq!(
+            mul_div_u64(
+                &program_id,
+                Some(calculator),
+                1_000_000,
+                2,
+                3,
+                Rounding::HalfUp,
+                &[signer_seeds]
+            ),
+            Ok(666_667)
+        );
+
+        // Without the authority's seeds the calculator cannot be modified
+        assert_eq!(
+            add(&program_id, Some(calculator), 1, 2, &[]),
+            Err(ProgramError::MissingRequiredSignature)
+        );
+
+        // Compute-only calls need no accounts
+        assert_eq!(multiply(&program_id, None, 6, 7, &[]), Ok(42));
+        assert_eq!(divide(&program_id, None, 7, 2, &[]), Ok(3));
+        assert_eq!(remainder(&program_id, None, 7, 2, &[]), Ok(1));
+        assert_eq!(signed_add(&program_id, None, -7, 4, &[]), Ok(-3));
+        assert_eq!(
+            u128_operation(&program_id, None, Operation::Add, u128::MAX - 1, 1, &[]),
+            Ok(u128::MAX)
+        );
+        assert_eq!(
+            mul_div_u128(&program_id, None, u128::MAX, 2, 4, Rounding::Ceil, &[]),
+            Ok(u128::MAX / 2 + 1)
+        );
+        assert_eq!(
+            divide(&program_id, None, 7, 0, &[]),
+            Err(CalculatorError::DivideByZero.into())
+        );
+    }
+}
@@ -0,0 +1,740 @@
+//! Fixed-point decimal numbers for arithmetic on fractional quantities
+//!
+//! A `Decimal` is the exact value `mantissa / 10^scale`. Operations compute
+//! their exact result and round it once to the requested scale, so every
+//! validator arrives at the same result.
+
+use crate::{
+    error::CalculatorError,
+    instruction::Operation,
+    math::{div_rounded, mul_div_pow10, mul_pow10_div, Rounding, U256},
+    pod::PodU128,
+};
+use borsh::{BorshDeserialize, BorshSerialize};
+use bytemuck::{Pod, Zeroable};
+use solana_program::msg;
+use std::{cmp::Ordering, fmt};
+
+/// Largest supported scale, the most decimal places a `u128` mantissa holds
+pub const MAX_SCALE: u8 = 38;
+
+/// A non-negative fixed-point decimal, `mantissa / 10^scale`
+///
+/// Borsh-encoded as the little-endian `u128` mantissa followed by the scale
+/// byte. A scale of 18 gives the common 18-decimal token amounts.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Decimal {
+    /// The value scaled by `10^scale`
+    pub mantissa: u128,
+    /// Number of decimal places, at most `MAX_SCALE`
+    pub scale: u8,
+}
+
+impl Decimal {
+    /// Size of the Borsh-encoded decimal in bytes
+    pub const LEN: usize = 16 + 1;
+
+    /// Create the decimal `mantissa / 10^scale`
+    pub fn new(mantissa: u128, scale: u8) -> Self {
+        Self { mantissa, scale }
+    }
+
+    /// Apply `operation` to `self` and `other`, rounding the exact result to
+    /// `scale` decimal places
+    pub fn checked_operation(
+        self,
+        operation: Operation,
+        other: Self,
+        scale: u8,
+        rounding: Rounding,
+    ) -> Result<Self, CalculatorError> {
+        if self.scale.max(other.scale).max(scale) > MAX_SCALE {
+            msg!("Decimal scale exceeds {}", MAX_SCALE);
+            return Err(CalculatorError::InvalidScale);
+        }
+
+        let mantissa = match operation {
+            // Both are exact in 256 bits at the larger scale, so they only
+            // overflow when the result does not fit at `scale`
+            Operation::Add => {
+                let (num1, num2, common_scale) = align_wide(self, other);
+                let sum = num1.checked_add(num2).ok_or_else(|| {
+                    msg!("Invalid decimal addition: result overflows");
+                    CalculatorError::Overflow
+                })?;
+                rescale_wide(sum, common_scale, scale, rounding)?
+            }
+            Operation::Subtract => {
+                let (num1, num2, common_scale) = align_wide(self, other);
+                let difference = num1.checked_sub(num2).ok_or_else(|| {
+                    msg!("Invalid decimal subtraction: num1 is less than num2");
+                    CalculatorError::Underflow
+                })?;
+                rescale_wide(difference, common_scale, scale, rounding)?
+            }
+            Operation::Multiply => {
+                let product_scale = self.scale + other.scale;
+                if scale >= product_scale {
+                    // Adding decimal places, so the product is below the result
+                    let product = self.mantissa.checked_mul(other.mantissa).ok_or_else(|| {
+                        msg!("Invalid decimal multiplication: result overflows");
+                        CalculatorError::Overflow
+                    })?;
+                    rescale(product, product_scale, scale, rounding)?
+                } else {
+                    // Divide the full 256-bit product straight down to `scale`
+                    let exponent = u32::from(product_scale - scale);
+                    mul_div_pow10(self.mantissa, other.mantissa, exponent, rounding).ok_or_else(
+                        || {
+                            msg!("Invalid decimal multiplication: result overflows");
+                            CalculatorError::Overflow
+                        },
+                    )?
+                }
+            }
+            Operation::Divide => {
+                if other.mantissa == 0 {
+                    msg!("Invalid decimal division: num2 is zero");
+                    return Err(CalculatorError::DivideByZero);
+                }
+                // num1 / num2 at `scale` is num1 * 10^(num2 scale + scale - num1 scale) / num2
+                let exponent = i32::from(other.scale) + i32::from(scale) - i32::from(self.scale);
+                if exponent >= 0 {
+                    // The scaled numerator is kept in 256 bits
+                    mul_pow10_div(self.mantissa, exponent as u32, other.mantissa, rounding)
+                        .ok_or_else(|| {
+                            msg!("Invalid decimal division: result overflows");
+                            CalculatorError::Overflow
+                        })?
+                } else {
+                    div_pow10_rounded(
+                        self.mantissa,
+                        other.mantissa,
+                        exponent.unsigned_abs(),
+                        rounding,
+                    )?
+                }
+            }
+            Operation::Remainder => {
+                if other.mantissa == 0 {
+                    msg!("Invalid decimal remainder: num2 is zero");
+                    return Err(CalculatorError::DivideByZero);
+                }
+                let (num1, num2, common_scale) = align(self, other)?;
+                rescale(num1 % num2, common_scale, scale, rounding)?
+            }
+        };
+        msg!(
+            "Decimal {:?} result: {}",
+            operation,
+            Self::new(mantissa, scale)
+        );
+
+        Ok(Self::new(mantissa, scale))
+    }
+}
+
+impl fmt::Display for Decimal {
+    /// Render the decimal with all of its `scale` decimal places
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let scale = usize::from(self.scale);
+        if scale == 0 {
+            return write!(f, "{}", self.mantissa);
+        }
+        let digits = format!("{:0>width$}", self.mantissa, width = scale + 1);
+        let (integer, fraction) = digits.split_at(digits.len() - scale);
+        write!(f, "{}.{}", integer, fraction)
+    }
+}
+
+/// Zero-copy view of a `Decimal` in account data
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Pod, Zeroable)]
+#[repr(C)]
+pub struct PodDecimal {
+    /// The value scaled by `10^scale`
+    pub mantissa: PodU128,
+    /// Number of decimal places
+    pub scale: u8,
+}
+
+impl From<Decimal> for PodDecimal {
+    fn from(value: Decimal) -> Self {
+        Self {
+            mantissa: value.mantissa.into(),
+            scale: value.scale,
+        }
+    }
+}
+
+impl From<PodDecimal> for Decimal {
+    fn from(value: PodDecimal) -> Self {
+        Self::new(value.mantissa.into(), value.scale)
+    }
+}
+
+// `10^exponent`, or `None` if it does not fit a `u128`
+fn pow10(exponent: u32) -> Option<u128> {
+    10u128.checked_pow(exponent)
+}
+
+// Both mantissas at the larger of the two scales, along with that scale
+fn align(num1: Decimal, num2: Decimal) -> Result<(u128, u128, u8), CalculatorError> {
+    let scale = num1.scale.max(num2.scale);
+    let widen = |num: Decimal| {
+        pow10(u32::from(scale - num.scale))
+            .and_then(|factor| num.mantissa.checked_mul(factor))
+            .ok_or_else(|| {
+                msg!("Decimal operand overflows at scale {}", scale);
+                CalculatorError::Overflow
+            })
+    };
+    Ok((widen(num1)?, widen(num2)?, scale))
+}
+
+// Both mantissas at the larger of the two scales in 256 bits, along with that
+// scale; a `u128` times a power of ten that fits one cannot exceed them
+fn align_wide(num1: Decimal, num2: Decimal) -> (U256, U256, u8) {
+    let scale = num1.scale.max(num2.scale);
+    let widen = |num: Decimal| U256::mul(num.mantissa, 10u128.pow(u32::from(scale - num.scale)));
+    (widen(num1), widen(num2), scale)
+}
+
+// Like `rescale` for a 256-bit mantissa, which must fit a `u128` once at `to`
+fn rescale_wide(
+    mantissa: U256,
+    from: u8,
+    to: u8,
+    rounding: Rounding,
+) -> Result<u128, CalculatorError> {
+    let overflow = || {
+        msg!("Decimal result overflows at scale {}", to);
+        CalculatorError::Overflow
+    };
+    if to >= from {
+        if mantissa.high != 0 {
+            return Err(overflow());
+        }
+        rescale(mantissa.low, from, to, rounding)
+    } else {
+        mantissa
+            .div_pow10(u32::from(from - to), rounding)
+            .ok_or_else(overflow)
+    }
+}
+
+// Convert a mantissa from one scale to another, rounding when decimal places
+// are dropped
+fn rescale(mantissa: u128, from: u8, to: u8, rounding: Rounding) -> Result<u128, CalculatorError> {
+    if to >= from {
+        pow10(u32::from(to - from))
+            .and_then(|factor| mantissa.checked_mul(factor))
+            .ok_or_else(|| {
+                msg!("Decimal result overflows at scale {}", to);
+                CalculatorError::Overflow
+            })
+    } else {
+        div_pow10_rounded(mantissa, 1, u32::from(from - to), rounding)
+    }
+}
+
+// `numerator / (divisor * 10^exponent)` rounded as requested, also when the
+// denominator exceeds a `u128` and the truncated quotient is zero
+fn div_pow10_rounded(
+    numerator: u128,
+    divisor: u128,
+    exponent: u32,
+    rounding: Rounding,
+) -> Result<u128, CalculatorError> {
+    if let Some(denominator) = pow10(exponent).and_then(|factor| divisor.checked_mul(factor)) {
+        return div_rounded(numerator, denominator, rounding).ok_or(CalculatorError::DivideByZero);
+    }
+    if numerator == 0 {
+        return Ok(0);
+    }
+
+    // The exponent is positive here, so half the denominator is the exact
+    // `divisor * 5 * 10^(exponent - 1)`, itself beyond `numerator` if too large
+    let half = pow10(exponent - 1)
+        .and_then(|factor| factor.checked_mul(5))
+        .and_then(|factor| divisor.checked_mul(factor));
+    let remainder = half.map_or(Ordering::Less, |half| numerator.cmp(&half));
+    Ok(rounding.rounds_away(false, remainder, false).into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::math::{
+        test::{pow10_wide, reference_div, reference_div_wide, reference_mul_div_pow10, ROUNDINGS},
+        U256,
+    };
+
+    #[test]
+    fn test_decimal_operations() {
+        let run = |operation, num1: Decimal, num2: Decimal, scale, rounding| {
+            num1.checked_operation(operation, num2, scale, rounding)
+        };
+        let one_and_half = Decimal::new(15, 1);
+        let quarter = Decimal::new(25, 2);
+
+        // Operands of different scales are aligned before the operation
+        assert_eq!(
+            run(Operation::Add, one_and_half, quarter, 2, Rounding::Floor),
+            Ok(Decimal::new(175, 2))
+        );
+        assert_eq!(
+            run(
+                Operation::Subtract,
+                one_and_half,
+                quarter,
+                3,
+                Rounding::Floor
+            ),
+            Ok(Decimal::new(1250, 3))
+        );
+        assert_eq!(
+            run(
+                Operation::Multiply,
+                one_and_half,
+                quarter,
+                3,
+                Rounding::Floor
+            ),
+            Ok(Decimal::new(375, 3))
+        );
+        assert_eq!(
+            run(Operation::Divide, one_and_half, quarter, 0, Rounding::Floor),
+            Ok(Decimal::new(6, 0))
+        );
+        assert_eq!(
+            run(
+                Operation::Remainder,
+                one_and_half,
+                quarter,
+                2,
+                Rounding::Floor
+            ),
+            Ok(Decimal::new(0, 2))
+        );
+
+        // Dropped decimal places are rounded explicitly
+        assert_eq!(
+            run(
+                Operation::Multiply,
+                one_and_half,
+                quarter,
+                2,
+                Rounding::Floor
+            ),
+            Ok(Decimal::new(37, 2))
+        );
+        assert_eq!(
+            run(
+                Operation::Multiply,
+                one_and_half,
+                quarter,
+                2,
+                Rounding::Ceil
+            ),
+            Ok(Decimal::new(38, 2))
+        );
+        let one = Decimal::new(1, 0);
+        let three = Decimal::new(3, 0);
+        assert_eq!(
+            run(Operation::Divide, one, three, 18, Rounding::Floor),
+            Ok(Decimal::new(333_333_333_333_333_333, 18))
+        );
+        assert_eq!(
+            run(Operation::Divide, one, three, 18, Rounding::Ceil),
+            Ok(Decimal::new(333_333_333_333_333_334, 18))
+        );
+
+        // Results far below the requested scale round to zero or one unit
+        let tiny = Decimal::new(1, MAX_SCALE);
+        let huge = Decimal::new(u128::MAX, 0);
+        assert_eq!(
+            run(Operation::Divide, tiny, huge, 0, Rounding::Floor),
+            Ok(Decimal::new(0, 0))
+        );
+        assert_eq!(
+            run(Operation::Divide, tiny, huge, 0, Rounding::Ceil),
+            Ok(Decimal::new(1, 0))
+        );
+        assert_eq!(
+            run(Operation::Multiply, tiny, tiny, 0, Rounding::Ceil),
+            Ok(Decimal::new(1, 0))
+        );
+
+        // Intermediate products beyond a `u128` do not overflow
+        let tokens = |whole: u128, thousandths: u128| {
+            Decimal::new(whole * 10u128.pow(18) + thousandths * 10u128.pow(15), 18)
+        };
+        assert_eq!(
+            run(
+                Operation::Multiply,
+                tokens(1_000_000, 500),
+                tokens(2_000, 250),
+                18,
+                Rounding::Floor
+            ),
+            Ok(tokens(2_000_251_000, 125))
+        );
+        assert_eq!(
+            run(
+                Operation::Divide,
+                tokens(1_000_000, 0),
+                tokens(3, 0),
+                18,
+                Rounding::HalfEven
+            ),
+            Ok(Decimal::new(333_333_333_333_333_333_333_333, 18))
+        );
+
+        // Errors
+        assert_eq!(
+            run(
+                Operation::Subtract,
+                quarter,
+                one_and_half,
+                2,
+                Rounding::Floor
+            ),
+            Err(CalculatorError::Underflow)
+        );
+        assert_eq!(
+            run(Operation::Add, huge, one, 0, Rounding::Floor),
+            Err(CalculatorError::Overflow)
+        );
+        assert_eq!(
+            run(Operation::Add, huge, quarter, 2, Rounding::Floor),
+            Err(CalculatorError::Overflow)
+        );
+        assert_eq!(
+            run(Operation::Add, huge, Decimal::default(), 1, Rounding::Floor),
+            Err(CalculatorError::Overflow)
+        );
+        assert_eq!(
+            run(
+                Operation::Divide,
+                one,
+                Decimal::new(0, 5),
+                2,
+                Rounding::Floor
+            ),
+            Err(CalculatorError::DivideByZero)
+        );
+        assert_eq!(
+            run(
+                Operation::Remainder,
+                one,
+                Decimal::default(),
+                2,
+                Rounding::Floor
+            ),
+            Err(CalculatorError::DivideByZero)
+        );
+        assert_eq!(
+            run(Operation::Add, one, one, MAX_SCALE + 1, Rounding::Floor),
+            Err(CalculatorError::InvalidScale)
+        );
+        assert_eq!(
+            run(
+                Operation::Add,
+                Decimal::new(1, MAX_SCALE + 1),
+                one,
+                0,
+                Rounding::Floor
+            ),
+            Err(CalculatorError::InvalidScale)
+        );
+    }
+
+    #[test]
+    fn test_decimal_rounding() {
+        let pow10 = |exponent: u8| 10i128.pow(exponent.into());
+        let decimals = || (0..=30).flat_map(|mantissa| (0..=2).map(move |scale| (mantissa, scale)));
+
+        // Every mode against the reference over small operands and scales
+        for rounding in ROUNDINGS {
+            for (m1, s1) in decimals() {
+                for (m2, s2) in decimals() {
+                    let num1 = Decimal::new(m1, s1);
+                    let num2 = Decimal::new(m2, s2);
+                    let (m1, m2) = (m1 as i128, m2 as i128);
+                    for scale in 0..=2 {
+                        let expected = |numerator, denominator| {
+                            Ok(Decimal::new(
+                                reference_div(numerator, denominator, rounding) as u128,
+                                scale,
+                            ))
+                        };
+                        let run =
+                            |operation| num1.checked_operation(operation, num2, scale, rounding);
+                        assert_eq!(
+                            run(Operation::Add),
+                            expected(
+                                (m1 * pow10(s2) + m2 * pow10(s1)) * pow10(scale),
+                                pow10(s1 + s2)
+                            )
+                        );
+                        assert_eq!(
+                            run(Operation::Multiply),
+                            expected(m1 * m2 * pow10(scale), pow10(s1 + s2))
+                        );
+                        if m2 != 0 {
+                            assert_eq!(
+                                run(Operation::Divide),
+                                expected(m1 * pow10(s2 + scale), m2 * pow10(s1)),
+                                "{} / {} at scale {} {:?}",
+                                num1,
+                                num2,
+                                scale,
+                                rounding
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
+        // Halfway cases stay exact when the divisor exceeds a `u128` at the
+        // result scale: 2 / 4 is divided as 2 * 10^38 / (4 * 10^38)
+        let half = 2 * 10u128.pow(38);
+        let four = Decimal::new(4, 0);
+        let cases = [
+            (half - 1, [0, 1, 0, 0]),
+            (half, [0, 1, 1, 0]),
+            (half + 1, [0, 1, 1, 1]),
+        ];
+        for (mantissa, expected) in cases {
+            for (rounding, expected) in ROUNDINGS.into_iter().zip(expected) {
+                assert_eq!(
+                    Decimal::new(mantissa, MAX_SCALE).checked_operation(
+                        Operation::Divide,
+                        four,
+                        0,
+                        rounding
+                    ),
+                    Ok(Decimal::new(expected, 0))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_decimal_multiply_large_scales() {
+        // 0.2 * 0.2 at 38 decimals each, a product scale of 76
+        let fifth = Decimal::new(2 * 10u128.pow(37), MAX_SCALE);
+        assert_eq!(
+            fifth.checked_operation(Operation::Multiply, fifth, 2, Rounding::Floor),
+            Ok(Decimal::new(4, 2))
+        );
+        assert_eq!(
+            fifth.checked_operation(Operation::Multiply, fifth, MAX_SCALE, Rounding::Floor),
+            Ok(Decimal::new(4 * 10u128.pow(36), MAX_SCALE))
+        );
+
+        // Pseudo-random operands whose scales sum past 38 against the
+        // reference rounding of the full product
+        let mut seed = 0x853c_49e6_748f_ea9b_u128;
+        let mut next = || {
+            seed = seed
+                .wrapping_mul(0x2360_ed05_1fc6_5da4_4385_df64_9fcc_f645)
+                .wrapping_add(0x5851_f42d_4c95_7f2d_1405_7b7e_f767_814f);
+            seed
+        };
+        let max_scale = u128::from(MAX_SCALE);
+        for _ in 0..1_000 {
+            let num1 = Decimal::new(next() >> (next() % 128), (next() % (max_scale + 1)) as u8);
+            let num2 = Decimal::new(next() >> (next() % 128), (next() % (max_scale + 1)) as u8);
+            let scale = (next() % (max_scale + 1)) as u8;
+            let product_scale = num1.scale + num2.scale;
+            if product_scale <= scale {
+                continue;
+            }
+            for rounding in ROUNDINGS {
+                let expected = reference_mul_div_pow10(
+                    num1.mantissa,
+                    num2.mantissa,
+                    (product_scale - scale).into(),
+                    rounding,
+                )
+                .map(|mantissa| Decimal::new(mantissa, scale))
+                .ok_or(CalculatorError::Overflow);
+                assert_eq!(
+                    num1.checked_operation(Operation::Multiply, num2, scale, rounding),
+                    expected,
+                    "{} * {} at {} {:?}",
+                    num1,
+                    num2,
+                    scale,
+                    rounding
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_decimal_divide_large_scales() {
+        // 1 / 1.0 at 20 decimals to 38 decimals scales the numerator by 10^58
+        let one = Decimal::new(1, 0);
+        let one_at_20 = Decimal::new(10u128.pow(20), 20);
+        assert_eq!(
+            one.checked_operation(Operation::Divide, one_at_20, MAX_SCALE, Rounding::Floor),
+            Ok(Decimal::new(10u128.pow(38), MAX_SCALE))
+        );
+
+        // Pseudo-random operands against the reference rounding of the full
+        // scaled numerator
+        let mut seed = 0xbb67_ae85_84ca_a73b_u128;
+        let mut next = || {
+            seed = seed
+                .wrapping_mul(0x2360_ed05_1fc6_5da4_4385_df64_9fcc_f645)
+                .wrapping_add(0x5851_f42d_4c95_7f2d_1405_7b7e_f767_814f);
+            seed
+        };
+        let max_scale = u128::from(MAX_SCALE);
+        for _ in 0..1_000 {
+            let num1 = Decimal::new(next() >> (next() % 128), (next() % (max_scale + 1)) as u8);
+            let num2 = Decimal::new(
+                (next() >> (next() % 128)).max(1),
+                (next() % (max_scale + 1)) as u8,
+            );
+            let scale = (next() % (max_scale + 1)) as u8;
+            if num2.scale + scale < num1.scale {
+                continue;
+            }
+            let exponent = u32::from(num2.scale + scale - num1.scale);
+            let denominator = U256 {
+                high: 0,
+                low: num2.mantissa,
+            };
+            for rounding in ROUNDINGS {
+                let expected = pow10_wide(exponent)
+                    .checked_mul(num1.mantissa)
+                    .and_then(|numerator| reference_div_wide(numerator, denominator, rounding))
+                    .map(|mantissa| Decimal::new(mantissa, scale))
+                    .ok_or(CalculatorError::Overflow);
+                assert_eq!(
+                    num1.checked_operation(Operation::Divide, num2, scale, rounding),
+                    expected,
+                    "{} / {} at {} {:?}",
+                    num1,
+                    num2,
+                    scale,
+                    rounding
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_decimal_add_subtract_large_scales() {
+        // Aligned to 38 decimals the operands exceed a `u128`, though the
+        // results fit at scale 0
+        let max = Decimal::new(u128::MAX, MAX_SCALE);
+        let one = Decimal::new(1, 0);
+        assert_eq!(
+            max.checked_operation(Operation::Add, one, 0, Rounding::Floor),
+            Ok(Decimal::new(4, 0))
+        );
+        assert_eq!(
+            Decimal::new(5, 0).checked_operation(Operation::Subtract, max, 0, Rounding::Ceil),
+            Ok(Decimal::new(2, 0))
+        );
+        assert_eq!(
+            Decimal::new(u128::MAX / 10, 0).checked_operation(
+                Operation::Add,
+                max,
+                0,
+                Rounding::HalfUp
+            ),
+            Ok(Decimal::new(u128::MAX / 10 + 3, 0))
+        );
+        assert_eq!(
+            max.checked_operation(Operation::Add, one, 1, Rounding::Floor),
+            Ok(Decimal::new(44, 1))
+        );
+        // Results that do not fit at the requested scale still overflow
+        assert_eq!(
+            Decimal::new(u128::MAX, 0).checked_operation(Operation::Add, max, 0, Rounding::Floor),
+            Err(CalculatorError::Overflow)
+        );
+        assert_eq!(
+            max.checked_operation(Operation::Add, one, MAX_SCALE, Rounding::Floor),
+            Err(CalculatorError::Overflow)
+        );
+
+        // Pseudo-random operands against the reference rounding of the exact
+        // 256-bit sum or difference
+        let mut seed = 0x3c6e_f372_fe94_f82b_u128;
+        let mut next = || {
+            seed = seed
+                .wrapping_mul(0x2360_ed05_1fc6_5da4_4385_df64_9fcc_f645)
+                .wrapping_add(0x5851_f42d_4c95_7f2d_1405_7b7e_f767_814f);
+            seed
+        };
+        let max_scale = u128::from(MAX_SCALE);
+        for _ in 0..1_000 {
+            let num1 = Decimal::new(next() >> (next() % 128), (next() % (max_scale + 1)) as u8);
+            let num2 = Decimal::new(next() >> (next() % 128), (next() % (max_scale + 1)) as u8);
+            let scale = (next() % (max_scale + 1)) as u8;
+            let common_scale = num1.scale.max(num2.scale);
+            let widen = |num: Decimal| {
+                pow10_wide((common_scale - num.scale).into())
+                    .checked_mul(num.mantissa)
+                    .unwrap()
+            };
+            let (wide1, wide2) = (widen(num1), widen(num2));
+            for (operation, exact) in [
+                (Operation::Add, wide1.checked_add(wide2)),
+                (Operation::Subtract, wide1.checked_sub(wide2)),
+            ] {
+                for rounding in ROUNDINGS {
+                    let expected = match exact {
+                        None => Err(CalculatorError::Underflow),
+                        Some(exact) if scale >= common_scale => exact
+                            .checked_mul(10u128.pow((scale - common_scale).into()))
+                            .filter(|mantissa| mantissa.high == 0)
+                            .map(|mantissa| Decimal::new(mantissa.low, scale))
+                            .ok_or(CalculatorError::Overflow),
+                        Some(exact) => reference_div_wide(
+                            exact,
+                            pow10_wide((common_scale - scale).into()),
+                            rounding,
+                        )
+                        .map(|mantissa| Decimal::new(mantissa, scale))
+                        .ok_or(CalculatorError::Overflow),
+                    };
+                    assert_eq!(
+                        num1.checked_operation(operation, num2, scale, rounding),
+                        expected,
+                        "{} {:?} {} at {} {:?}",
+                        num1,
+                        operation,
+                        num2,
+                        scale,
+                        rounding
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_decimal_display() {
+        assert_eq!(Decimal::new(0, 0).to_string(), "0");
+        assert_eq!(Decimal::new(1234, 0).to_string(), "1234");
+        assert_eq!(Decimal::new(1234, 2).to_string(), "12.34");
+        assert_eq!(Decimal::new(5, 3).to_string(), "0.005");
+        assert_eq!(Decimal::new(1500, 3).to_string(), "1.500");
+        assert_eq!(
+            Decimal::new(10u128.pow(18), 18).to_string(),
+            "1.000000000000000000"
+        );
+        assert_eq!(
+            Decimal::new(u128::MAX, MAX_SCALE).to_string(),
+            "3.40282366920938463463374607431768211455"
+        );
+    }
+}
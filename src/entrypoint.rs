@@ -0,0 +1,27 @@
+//! Program entrypoint
+
+// `entrypoint!` expands to cfgs on the Solana target and on heap and panic
+// features of its own, which rustc does not know to expect
+#![allow(unexpected_cfgs)]
+
+use crate::{error::CalculatorError, processor::handle_instruction};
+use solana_program::{
+    account_info::AccountInfo, entrypoint, entrypoint::ProgramResult,
+    program_error::PrintProgramError, pubkey::Pubkey,
+};
+
+// Declare and export the program's entrypoint
+entrypoint!(process_instruction);
+
+// Log calculator errors in human-readable form before returning them
+fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    if let Err(error) = handle_instruction(program_id, accounts, instruction_data) {
+        error.print::<CalculatorError>();
+        return Err(error);
+    }
+    Ok(())
+}
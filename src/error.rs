@@ -0,0 +1,122 @@
+use num_derive::FromPrimitive;
+use solana_program::{
+    decode_error::DecodeError,
+    msg,
+    program_error::{PrintProgramError, ProgramError},
+};
+use thiserror::Error;
+
+/// Errors that may be returned by the calculator program
+///
+/// The discriminants are the `ProgramError::Custom` codes reported on chain and
+/// must never be reordered or reused.
+#[derive(Clone, Copy, Debug, Eq, Error, FromPrimitive, PartialEq)]
+pub enum CalculatorError {
+    /// The divisor of a division or remainder operation was zero
+    #[error("Division by zero")]
+    DivideByZero = 0,
+    /// The result of an operation does not fit in its result type
+    #[error("Arithmetic overflow")]
+    Overflow = 1,
+    /// The result of an operation is below the minimum of its result type
+    #[error("Arithmetic underflow")]
+    Underflow = 2,
+    /// The instruction tag or legacy operation code is not recognised
+    #[error("Unknown operation")]
+    UnknownOperation = 3,
+    /// The instruction data is empty, truncated or has trailing bytes
+    #[error("Invalid instruction data")]
+    InvalidInstruction = 4,
+    /// The calculator account does not hold calculator state
+    #[error("Calculator account is not initialized")]
+    AccountNotInitialized = 5,
+    /// The calculator account is not owned by the calculator program
+    #[error("Calculator account is not owned by the program")]
+    IncorrectAccountOwner = 6,
+    /// `Initialize` was sent for an account that is already initialized
+    #[error("Calculator account is already initialized")]
+    AccountAlreadyInitialized = 7,
+    /// The account data belongs to a different account type
+    #[error("Account is not a calculator account")]
+    InvalidAccountType = 8,
+    /// The account data was written with an unsupported layout version
+    #[error("Unsupported calculator account version")]
+    UnsupportedAccountVersion = 9,
+    /// The signer is not the calculator account's authority
+    #[error("Signer is not the calculator account authority")]
+    InvalidAuthority = 10,
+    /// An invoked calculator did not return the expected result
+    #[error("Calculator did not return the expected result")]
+    InvalidReturnData = 11,
+    /// A decimal operand or result has more decimal places than supported
+    #[error("Decimal scale exceeds the maximum")]
+    InvalidScale = 12,
+    /// An integer root of degree zero was requested
+    #[error("Root degree must be at least one")]
+    InvalidDegree = 13,
+}
+
+impl From<CalculatorError> for ProgramError {
+    fn from(e: CalculatorError) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
+
+impl<T> DecodeError<T> for CalculatorError {
+    fn type_of() -> &'static str {
+        "CalculatorError"
+    }
+}
+
+impl PrintProgramError for CalculatorError {
+    fn print<E>(&self)
+    where
+        E: 'static
+            + std::error::Error
+            + DecodeError<E>
+            + PrintProgramError
+            + num_traits::FromPrimitive,
+    {
+        msg!("Error: {}", self);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_error_codes_are_stable() {
+        let errors = [
+            (CalculatorError::DivideByZero, 0),
+            (CalculatorError::Overflow, 1),
+            (CalculatorError::Underflow, 2),
+            (CalculatorError::UnknownOperation, 3),
+            (CalculatorError::InvalidInstruction, 4),
+            (CalculatorError::AccountNotInitialized, 5),
+            (CalculatorError::IncorrectAccountOwner, 6),
+            (CalculatorError::AccountAlreadyInitialized, 7),
+            (CalculatorError::InvalidAccountType, 8),
+            (CalculatorError::UnsupportedAccountVersion, 9),
+            (CalculatorError::InvalidAuthority, 10),
+            (CalculatorError::InvalidReturnData, 11),
+            (CalculatorError::InvalidScale, 12),
+            (CalculatorError::InvalidDegree, 13),
+        ];
+
+        for (error, code) in errors {
+            assert_eq!(ProgramError::from(error), ProgramError::Custom(code));
+            assert_eq!(
+                <CalculatorError as DecodeError<CalculatorError>>::decode_custom_error_to_enum(
+                    code
+                ),
+                Some(error)
+            );
+        }
+
+        assert_eq!(
+            <CalculatorError as DecodeError<CalculatorError>>::decode_custom_error_to_enum(1000),
+            None
+        );
+    }
+}
@@ -0,0 +1,1515 @@
+use crate::{
+    decimal::Decimal, error::CalculatorError, math::Rounding, state::find_calculator_address,
+};
+use borsh::{de::EnumExt, BorshDeserialize, BorshSerialize};
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    msg,
+    program::get_return_data,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    system_program,
+};
+
+/// Size of the legacy `[num1, num2, operation]` instruction layout
+///
+/// Payloads of exactly this length whose trailing `u32` is a legacy operation
+/// code (0 or 1) are decoded as the legacy layout, unless they are also a valid
+/// Borsh encoding.
+pub const LEGACY_INSTRUCTION_LEN: usize = 12;
+
+/// Instructions supported by the calculator program
+///
+/// Each instruction is Borsh-encoded: a one byte variant tag followed by the
+/// variant's operands. Arithmetic instructions also publish their result as
+/// an `OperationResult` through the transaction return data, and may be sent
+/// without any accounts to only compute the result without storing it.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub enum CalculatorInstruction {
+    /// Store `num1 + num2` in the calculator account
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The calculator account
+    /// 1. `[signer]` The calculator account's authority
+    Add { num1: u32, num2: u32 },
+
+    /// Store `num1 - num2` in the calculator account
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The calculator account
+    /// 1. `[signer]` The calculator account's authority
+    Subtract { num1: u32, num2: u32 },
+
+    /// Store `num1 * num2` in the calculator account
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The calculator account
+    /// 1. `[signer]` The calculator account's authority
+    Multiply { num1: u32, num2: u32 },
+
+    /// Store the integer quotient `num1 / num2` in the calculator account,
+    /// rounded down; `DivideRounded` selects the rounding
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The calculator account
+    /// 1. `[signer]` The calculator account's authority
+    Divide { num1: u32, num2: u32 },
+
+    /// Store the remainder `num1 % num2` in the calculator account
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The calculator account
+    /// 1. `[signer]` The calculator account's authority
+    Remainder { num1: u32, num2: u32 },
+
+    /// Store the signed sum `num1 + num2` in the calculator account
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The calculator account
+    /// 1. `[signer]` The calculator account's authority
+    SignedAdd { num1: i64, num2: i64 },
+
+    /// Store the signed difference `num1 - num2` in the calculator account,
+    /// which may be negative
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The calculator account
+    /// 1. `[signer]` The calculator account's authority
+    SignedSubtract { num1: i64, num2: i64 },
+
+    /// Write the calculator state header and zero all results
+    ///
+    /// Every other instruction rejects accounts that have not been
+    /// initialized. The account records its last `history_capacity`
+    /// operations and must hold `CalcResult::space(history_capacity)` bytes.
+    ///
+    /// The calculator account must sign, so that only whoever created it can
+    /// choose its authority.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable, signer]` The calculator account, owned by the program
+    /// 1. `[signer]` The authority to record for the account
+    Initialize { history_capacity: u32 },
+
+    /// Hand the calculator account over to a new authority
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The calculator account
+    /// 1. `[signer]` The calculator account's current authority
+    SetAuthority { new_authority: Pubkey },
+
+    /// Create and initialize the signing user's calculator account at the
+    /// program-derived address `find_calculator_address(program_id, user, label)`
+    ///
+    /// The label may be empty and is at most 32 bytes. The account is sized to
+    /// record the last `history_capacity` operations. The user funds the rent-exempt balance and becomes the
+    /// account's authority.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The calculator account's derived address
+    /// 1. `[writable, signer]` The user
+    /// 2. `[]` The system program
+    // The capacity comes first so that the only 12-byte encodings that look
+    // like the legacy layout are those with the label "\0\0\0"
+    CreateCalculator {
+        history_capacity: u32,
+        label: String,
+    },
+
+    /// Close the calculator account, zeroing its data and sending all of its
+    /// lamports to the destination
+    ///
+    /// The account is handed back to the system program, so the calculator
+    /// refuses to use it again.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The calculator account
+    /// 1. `[signer]` The calculator account's authority
+    /// 2. `[writable]` The destination for the reclaimed lamports
+    Close,
+
+    /// Add the last result into the memory register (M+)
+    ///
+    /// Publishes the new memory value as a signed `OperationResult`.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The calculator account
+    /// 1. `[signer]` The calculator account's authority
+    MemoryAdd,
+
+    /// Subtract the last result from the memory register (M-)
+    ///
+    /// Publishes the new memory value as a signed `OperationResult`.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The calculator account
+    /// 1. `[signer]` The calculator account's authority
+    MemorySubtract,
+
+    /// Recall the memory register (MR), making it the last result and the
+    /// accumulator value
+    ///
+    /// Following accumulator instructions operate on the recalled value, so
+    /// it is used as an operand without the client reading the account.
+    /// Publishes the memory value as a signed `OperationResult`.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The calculator account
+    /// 1. `[signer]` The calculator account's authority
+    MemoryRecall,
+
+    /// Clear the memory register (MC)
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The calculator account
+    /// 1. `[signer]` The calculator account's authority
+    MemoryClear,
+
+    /// Add `num` to the accumulator value
+    ///
+    /// Publishes the new value as a signed `OperationResult`.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The calculator account
+    /// 1. `[signer]` The calculator account's authority
+    AccumulatorAdd { num: i64 },
+
+    /// Subtract `num` from the accumulator value
+    ///
+    /// Publishes the new value as a signed `OperationResult`.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The calculator account
+    /// 1. `[signer]` The calculator account's authority
+    AccumulatorSubtract { num: i64 },
+
+    /// Multiply the accumulator value by `num`
+    ///
+    /// Publishes the new value as a signed `OperationResult`.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The calculator account
+    /// 1. `[signer]` The calculator account's authority
+    AccumulatorMultiply { num: i64 },
+
+    /// Divide the accumulator value by `num`, rounding toward zero;
+    /// `AccumulatorDivideRounded` selects the rounding
+    ///
+    /// Publishes the new value as a signed `OperationResult`.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The calculator account
+    /// 1. `[signer]` The calculator account's authority
+    AccumulatorDivide { num: i64 },
+
+    /// Set the accumulator value, e.g. to reset a running total
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The calculator account
+    /// 1. `[signer]` The calculator account's authority
+    AccumulatorSet { value: i64 },
+
+    /// Grow or shrink the calculator account to record the last
+    /// `history_capacity` operations, keeping the newest recorded ones
+    ///
+    /// The payer tops the account up to its new rent-exempt balance, and
+    /// receives any lamports above it when the account shrinks. An account
+    /// can grow by at most 10 KiB per instruction.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The calculator account
+    /// 1. `[signer]` The calculator account's authority
+    /// 2. `[writable, signer]` The payer
+    /// 3. `[]` The system program
+    Resize { history_capacity: u32 },
+
+    /// Upgrade a calculator account written with an older state layout to
+    /// the current one in place, keeping its results
+    ///
+    /// Version 1 accounts had no authority, so the signing authority is
+    /// recorded for them and the calculator account itself must sign to
+    /// prove ownership; later versions must be migrated by their recorded
+    /// authority. Recorded history entries are converted to the current
+    /// entry layout; if they would grow the account by more than one
+    /// reallocation allows, the history capacity shrinks to fit and the
+    /// oldest entries are dropped, as by `Resize`. The payer tops up the rent
+    /// of the grown account.
+    /// Accounts already on the current layout are left unchanged.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The calculator account, also `[signer]` for version 1
+    /// 1. `[signer]` The calculator account's authority
+    /// 2. `[writable, signer]` The payer
+    /// 3. `[]` The system program
+    Migrate,
+
+    /// Store `num1 <operation> num2` for unsigned operands of the selected
+    /// width
+    ///
+    /// `u32` results are stored and published like those of `Add`,
+    /// `Subtract`, `Multiply`, `Divide` and `Remainder`. `u64` and `u128`
+    /// results are stored in the result field of their width and published
+    /// as `U64` and `U128` results; they do not fit the `i64` memory register,
+    /// so they leave the last result unchanged.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The calculator account
+    /// 1. `[signer]` The calculator account's authority
+    Arithmetic {
+        operation: Operation,
+        operands: Operands,
+    },
+
+    /// Store the fixed-point decimal `num1 <operation> num2`, rounded to
+    /// `scale` decimal places as `rounding` says
+    ///
+    /// Operands and result have at most `decimal::MAX_SCALE` decimal places.
+    /// The result is published as a `Decimal` result and, like wide results,
+    /// leaves the last result unchanged.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The calculator account
+    /// 1. `[signer]` The calculator account's authority
+    DecimalArithmetic {
+        operation: Operation,
+        num1: Decimal,
+        num2: Decimal,
+        scale: u8,
+        rounding: Rounding,
+    },
+
+    /// Store `num1 / num2` for unsigned operands of the selected width,
+    /// rounded as `rounding` says
+    ///
+    /// Results are stored and published like those of `Arithmetic`.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The calculator account
+    /// 1. `[signer]` The calculator account's authority
+    DivideRounded {
+        operands: Operands,
+        rounding: Rounding,
+    },
+
+    /// Divide the accumulator value by `num`, rounded as `rounding` says
+    ///
+    /// Publishes the new value as a signed `OperationResult`.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The calculator account
+    /// 1. `[signer]` The calculator account's authority
+    AccumulatorDivideRounded { num: i64, rounding: Rounding },
+
+    /// Store `num1 * num2 / denominator` for unsigned operands of the
+    /// selected width, rounded as `rounding` says
+    ///
+    /// The product is computed in 256 bits, so the result is exact whenever
+    /// it fits the operand width, as needed for pro-rata shares and fees.
+    /// Results are stored and published like those of `Arithmetic`.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The calculator account
+    /// 1. `[signer]` The calculator account's authority
+    MulDiv {
+        operands: MulDivOperands,
+        rounding: Rounding,
+    },
+
+    /// Store `base` raised to `exponent` for an unsigned base of the selected
+    /// width
+    ///
+    /// Computed by squaring in at most 13 checked multiplications for `u128`
+    /// bases and 11 for `u64` bases, reached by a base of zero or one with
+    /// `exponent` 127 or 63; larger exponents only have powers that fit for
+    /// bases of zero and one, and are reduced first. `0^0` is one. Consumes
+    /// at most 20,000 compute units. Results are stored and published like
+    /// those of `MulDiv`.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The calculator account
+    /// 1. `[signer]` The calculator account's authority
+    Pow { base: Operand, exponent: u32 },
+
+    /// Store the integer square root of `value`, rounded down
+    ///
+    /// Computed without multiplications in at most 64 steps, reached by
+    /// `u128::MAX`. Consumes at most 20,000 compute units. Results are stored
+    /// and published like those of `MulDiv`.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The calculator account
+    /// 1. `[signer]` The calculator account's authority
+    Isqrt { value: Operand },
+
+    /// Store the integer `degree`-th root of `value`, rounded down
+    ///
+    /// Takes at most 129 checked multiplications for `u128` values and 66
+    /// for `u64` values, reached by the cube root of `u128::MAX` and
+    /// `u64::MAX`. Square roots are computed like `Isqrt`, and degrees of at
+    /// least the bit width need no work. Consumes at most 50,000 compute
+    /// units. Results are stored and published like those of `MulDiv`.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` The calculator account
+    /// 1. `[signer]` The calculator account's authority
+    NthRoot { value: Operand, degree: u32 },
+}
+
+impl CalculatorInstruction {
+    // Number of instruction variants, one more than the largest tag
+    const VARIANT_COUNT: u8 = 30;
+
+    /// Size of the longest Borsh-encoded instruction in bytes, `MulDiv` on
+    /// `u128` operands
+    pub const MAX_LEN: usize = 1 + 1 + 3 * 16 + 1;
+
+    /// Unpack an instruction from the raw instruction data
+    ///
+    /// Accepts both the Borsh-encoded format and the legacy 12-byte layout.
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        if Self::is_legacy(input) {
+            return Self::unpack_legacy(input);
+        }
+
+        let (&tag, mut rest) = input.split_first().ok_or_else(|| {
+            msg!("Instruction data is empty");
+            CalculatorError::InvalidInstruction
+        })?;
+
+        if tag >= Self::VARIANT_COUNT {
+            msg!("Unknown instruction tag: {}", tag);
+            return Err(CalculatorError::UnknownOperation.into());
+        }
+
+        // The tag is known, so any failure is in the operands, including the
+        // tags of nested enums
+        let instruction = Self::deserialize_variant(&mut rest, tag).map_err(|_| {
+            msg!("Malformed operands for instruction tag {}", tag);
+            CalculatorError::InvalidInstruction
+        })?;
+
+        if !rest.is_empty() {
+            msg!("Instruction data has {} trailing bytes", rest.len());
+            return Err(CalculatorError::InvalidInstruction.into());
+        }
+
+        Ok(instruction)
+    }
+
+    /// Whether the input uses the legacy layout rather than Borsh
+    ///
+    /// Valid Borsh encodings win. The only ones that also have the legacy
+    /// shape are `CreateCalculator` with the label `"\0\0\0"` and any
+    /// capacity, which read as a legacy `Add` whose `num1` is 9 modulo 256 and
+    /// whose `num2` is in `0x300..=0x3FF`, so those operands cannot be added
+    /// through the legacy layout.
+    fn is_legacy(input: &[u8]) -> bool {
+        input.len() == LEGACY_INSTRUCTION_LEN
+            && matches!(input[8..], [0 | 1, 0, 0, 0])
+            && Self::try_from_slice(input).is_err()
+    }
+
+    /// Unpack the legacy layout of three little-endian `u32`s
+    /// `[num1, num2, operation]`, where operation 0 adds and 1 subtracts
+    fn unpack_legacy(input: &[u8]) -> Result<Self, ProgramError> {
+        let num1 = u32::from_le_bytes(input[0..4].try_into().unwrap());
+        let num2 = u32::from_le_bytes(input[4..8].try_into().unwrap());
+        let operation = u32::from_le_bytes(input[8..12].try_into().unwrap());
+
+        if operation == 0 {
+            Ok(Self::Add { num1, num2 })
+        } else {
+            Ok(Self::Subtract { num1, num2 })
+        }
+    }
+
+    /// Pack the instruction into its Borsh-encoded wire format
+    pub fn pack(&self) -> Vec<u8> {
+        self.try_to_vec().unwrap()
+    }
+}
+
+/// Operation applied by `CalculatorInstruction::Arithmetic` and
+/// `CalculatorInstruction::DecimalArithmetic`
+///
+/// Borsh-encoded as a one byte tag in declaration order.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    /// `num1 + num2`
+    Add,
+    /// `num1 - num2`
+    Subtract,
+    /// `num1 * num2`
+    Multiply,
+    /// The integer quotient `num1 / num2`
+    Divide,
+    /// The remainder `num1 % num2`
+    Remainder,
+}
+
+/// Unsigned operands of `CalculatorInstruction::Arithmetic` in a selectable
+/// width
+///
+/// Borsh-encoded as a one byte width tag (0 for `u32`, 1 for `u64`, 2 for
+/// `u128`) followed by both little-endian operands.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operands {
+    U32 { num1: u32, num2: u32 },
+    U64 { num1: u64, num2: u64 },
+    U128 { num1: u128, num2: u128 },
+}
+
+/// Unsigned operands of `CalculatorInstruction::MulDiv` in a selectable width
+///
+/// Borsh-encoded as a one byte width tag (0 for `u64`, 1 for `u128`) followed
+/// by the three little-endian operands.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MulDivOperands {
+    U64 {
+        num1: u64,
+        num2: u64,
+        denominator: u64,
+    },
+    U128 {
+        num1: u128,
+        num2: u128,
+        denominator: u128,
+    },
+}
+
+/// Unsigned operand of `CalculatorInstruction::Pow`, `Isqrt` and `NthRoot`
+/// in a selectable width
+///
+/// Borsh-encoded as a one byte width tag (0 for `u64`, 1 for `u128`) followed
+/// by the little-endian operand.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    U64(u64),
+    U128(u128),
+}
+
+/// Result of an arithmetic instruction, published with `set_return_data`
+///
+/// Borsh-encoded as a one byte tag followed by the little-endian value:
+/// tag 0 for an unsigned `u32` result, tag 1 for a signed `i64` result, tag 2
+/// for a `u64` result, tag 3 for a `u128` result and tag 4 for a `Decimal`
+/// result.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperationResult {
+    /// Result of `Add`, `Subtract`, `Multiply`, `Divide` or `Remainder`, or of
+    /// `Arithmetic` on `u32` operands
+    Unsigned(u32),
+    /// Result of `SignedAdd` or `SignedSubtract`, the memory register or the
+    /// accumulator value
+    Signed(i64),
+    /// Result of `Arithmetic`, `MulDiv`, `Pow`, `Isqrt` or `NthRoot` on
+    /// `u64` operands
+    U64(u64),
+    /// Result of `Arithmetic`, `MulDiv`, `Pow`, `Isqrt` or `NthRoot` on
+    /// `u128` operands
+    U128(u128),
+    /// Result of `DecimalArithmetic`
+    Decimal(Decimal),
+}
+
+impl OperationResult {
+    /// Size of the longest Borsh-encoded result in bytes
+    pub const MAX_LEN: usize = 1 + Decimal::LEN;
+
+    /// Read the result published by the calculator program `program_id`, for
+    /// use by callers right after invoking it
+    pub fn from_return_data(program_id: &Pubkey) -> Option<Self> {
+        let (returning_program, data) = get_return_data()?;
+        if returning_program != *program_id {
+            return None;
+        }
+        Self::try_from_slice(&data).ok()
+    }
+}
+
+/// Creates an `Initialize` instruction
+pub fn initialize(
+    program_id: &Pubkey,
+    calculator: &Pubkey,
+    authority: &Pubkey,
+    history_capacity: u32,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &CalculatorInstruction::Initialize { history_capacity }.pack(),
+        vec![
+            AccountMeta::new(*calculator, true),
+            AccountMeta::new_readonly(*authority, true),
+        ],
+    )
+}
+
+/// Creates a `SetAuthority` instruction
+pub fn set_authority(
+    program_id: &Pubkey,
+    calculator: &Pubkey,
+    authority: &Pubkey,
+    new_authority: &Pubkey,
+) -> Instruction {
+    let data = CalculatorInstruction::SetAuthority {
+        new_authority: *new_authority,
+    };
+    operation(program_id, calculator, authority, data)
+}
+
+/// Creates a `CreateCalculator` instruction for the user's calculator account
+/// with the given label
+pub fn create_calculator(
+    program_id: &Pubkey,
+    user: &Pubkey,
+    label: &str,
+    history_capacity: u32,
+) -> Instruction {
+    let (calculator, _) = find_calculator_address(program_id, user, label);
+    Instruction::new_with_bytes(
+        *program_id,
+        &CalculatorInstruction::CreateCalculator {
+            history_capacity,
+            label: label.to_string(),
+        }
+        .pack(),
+        vec![
+            AccountMeta::new(calculator, false),
+            AccountMeta::new(*user, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+/// Creates a `Close` instruction
+pub fn close(
+    program_id: &Pubkey,
+    calculator: &Pubkey,
+    authority: &Pubkey,
+    destination: &Pubkey,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &CalculatorInstruction::Close.pack(),
+        vec![
+            AccountMeta::new(*calculator, false),
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(*destination, false),
+        ],
+    )
+}
+
+/// Creates a `Resize` instruction
+pub fn resize(
+    program_id: &Pubkey,
+    calculator: &Pubkey,
+    authority: &Pubkey,
+    payer: &Pubkey,
+    history_capacity: u32,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &CalculatorInstruction::Resize { history_capacity }.pack(),
+        vec![
+            AccountMeta::new(*calculator, false),
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+/// Creates a `Migrate` instruction
+pub fn migrate(
+    program_id: &Pubkey,
+    calculator: &Pubkey,
+    authority: &Pubkey,
+    payer: &Pubkey,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &CalculatorInstruction::Migrate.pack(),
+        vec![
+            AccountMeta::new(*calculator, false),
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+/// Creates a `Migrate` instruction for a version 1 calculator account, which
+/// signs to prove ownership and records `authority` as its authority
+pub fn migrate_v1(
+    program_id: &Pubkey,
+    calculator: &Pubkey,
+    authority: &Pubkey,
+    payer: &Pubkey,
+) -> Instruction {
+    let mut instruction = migrate(program_id, calculator, authority, payer);
+    instruction.accounts[0].is_signer = true;
+    instruction
+}
+
+/// Creates an instruction that applies `instruction` to the calculator
+/// account, signed by its authority
+pub fn operation(
+    program_id: &Pubkey,
+    calculator: &Pubkey,
+    authority: &Pubkey,
+    instruction: CalculatorInstruction,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &instruction.pack(),
+        vec![
+            AccountMeta::new(*calculator, false),
+            AccountMeta::new_readonly(*authority, true),
+        ],
+    )
+}
+
+/// Creates a compute-only arithmetic instruction that takes no accounts and
+/// only returns its result
+pub fn compute(program_id: &Pubkey, instruction: CalculatorInstruction) -> Instruction {
+    Instruction::new_with_bytes(*program_id, &instruction.pack(), vec![])
+}
+
+/// Creates a `MemoryAdd` instruction
+pub fn memory_add(program_id: &Pubkey, calculator: &Pubkey, authority: &Pubkey) -> Instruction {
+    operation(
+        program_id,
+        calculator,
+        authority,
+        CalculatorInstruction::MemoryAdd,
+    )
+}
+
+/// Creates a `MemorySubtract` instruction
+pub fn memory_subtract(
+    program_id: &Pubkey,
+    calculator: &Pubkey,
+    authority: &Pubkey,
+) -> Instruction {
+    operation(
+        program_id,
+        calculator,
+        authority,
+        CalculatorInstruction::MemorySubtract,
+    )
+}
+
+/// Creates a `MemoryRecall` instruction
+pub fn memory_recall(program_id: &Pubkey, calculator: &Pubkey, authority: &Pubkey) -> Instruction {
+    operation(
+        program_id,
+        calculator,
+        authority,
+        CalculatorInstruction::MemoryRecall,
+    )
+}
+
+/// Creates a `MemoryClear` instruction
+pub fn memory_clear(program_id: &Pubkey, calculator: &Pubkey, authority: &Pubkey) -> Instruction {
+    operation(
+        program_id,
+        calculator,
+        authority,
+        CalculatorInstruction::MemoryClear,
+    )
+}
+
+/// Creates an `AccumulatorAdd` instruction
+pub fn accumulator_add(
+    program_id: &Pubkey,
+    calculator: &Pubkey,
+    authority: &Pubkey,
+    num: i64,
+) -> Instruction {
+    let data = CalculatorInstruction::AccumulatorAdd { num };
+    operation(program_id, calculator, authority, data)
+}
+
+/// Creates an `AccumulatorSubtract` instruction
+pub fn accumulator_subtract(
+    program_id: &Pubkey,
+    calculator: &Pubkey,
+    authority: &Pubkey,
+    num: i64,
+) -> Instruction {
+    let data = CalculatorInstruction::AccumulatorSubtract { num };
+    operation(program_id, calculator, authority, data)
+}
+
+/// Creates an `AccumulatorMultiply` instruction
+pub fn accumulator_multiply(
+    program_id: &Pubkey,
+    calculator: &Pubkey,
+    authority: &Pubkey,
+    num: i64,
+) -> Instruction {
+    let data = CalculatorInstruction::AccumulatorMultiply { num };
+    operation(program_id, calculator, authority, data)
+}
+
+/// Creates an `AccumulatorDivide` instruction
+pub fn accumulator_divide(
+    program_id: &Pubkey,
+    calculator: &Pubkey,
+    authority: &Pubkey,
+    num: i64,
+) -> Instruction {
+    let data = CalculatorInstruction::AccumulatorDivide { num };
+    operation(program_id, calculator, authority, data)
+}
+
+/// Creates an `AccumulatorSet` instruction
+pub fn accumulator_set(
+    program_id: &Pubkey,
+    calculator: &Pubkey,
+    authority: &Pubkey,
+    value: i64,
+) -> Instruction {
+    let data = CalculatorInstruction::AccumulatorSet { value };
+    operation(program_id, calculator, authority, data)
+}
+
+/// Creates an `Arithmetic` instruction
+pub fn arithmetic(
+    program_id: &Pubkey,
+    calculator: &Pubkey,
+    authority: &Pubkey,
+    operation: Operation,
+    operands: Operands,
+) -> Instruction {
+    let data = CalculatorInstruction::Arithmetic {
+        operation,
+        operands,
+    };
+    self::operation(program_id, calculator, authority, data)
+}
+
+/// Creates a `DecimalArithmetic` instruction
+#[allow(clippy::too_many_arguments)]
+pub fn decimal_arithmetic(
+    program_id: &Pubkey,
+    calculator: &Pubkey,
+    authority: &Pubkey,
+    operation: Operation,
+    num1: Decimal,
+    num2: Decimal,
+    scale: u8,
+    rounding: Rounding,
+) -> Instruction {
+    let data = CalculatorInstruction::DecimalArithmetic {
+        operation,
+        num1,
+        num2,
+        scale,
+        rounding,
+    };
+    self::operation(program_id, calculator, authority, data)
+}
+
+/// Creates a `DivideRounded` instruction
+pub fn divide_rounded(
+    program_id: &Pubkey,
+    calculator: &Pubkey,
+    authority: &Pubkey,
+    operands: Operands,
+    rounding: Rounding,
+) -> Instruction {
+    let data = CalculatorInstruction::DivideRounded { operands, rounding };
+    operation(program_id, calculator, authority, data)
+}
+
+/// Creates an `AccumulatorDivideRounded` instruction
+pub fn accumulator_divide_rounded(
+    program_id: &Pubkey,
+    calculator: &Pubkey,
+    authority: &Pubkey,
+    num: i64,
+    rounding: Rounding,
+) -> Instruction {
+    let data = CalculatorInstruction::AccumulatorDivideRounded { num, rounding };
+    operation(program_id, calculator, authority, data)
+}
+
+/// Creates a `MulDiv` instruction
+pub fn mul_div(
+    program_id: &Pubkey,
+    calculator: &Pubkey,
+    authority: &Pubkey,
+    operands: MulDivOperands,
+    rounding: Rounding,
+) -> Instruction {
+    let data = CalculatorInstruction::MulDiv { operands, rounding };
+    operation(program_id, calculator, authority, data)
+}
+
+/// Creates a `Pow` instruction
+pub fn pow(
+    program_id: &Pubkey,
+    calculator: &Pubkey,
+    authority: &Pubkey,
+    base: Operand,
+    exponent: u32,
+) -> Instruction {
+    let data = CalculatorInstruction::Pow { base, exponent };
+    operation(program_id, calculator, authority, data)
+}
+
+/// Creates an `Isqrt` instruction
+pub fn isqrt(
+    program_id: &Pubkey,
+    calculator: &Pubkey,
+    authority: &Pubkey,
+    value: Operand,
+) -> Instruction {
+    let data = CalculatorInstruction::Isqrt { value };
+    operation(program_id, calculator, authority, data)
+}
+
+/// Creates an `NthRoot` instruction
+pub fn nth_root(
+    program_id: &Pubkey,
+    calculator: &Pubkey,
+    authority: &Pubkey,
+    value: Operand,
+    degree: u32,
+) -> Instruction {
+    let data = CalculatorInstruction::NthRoot { value, degree };
+    operation(program_id, calculator, authority, data)
+}
+
+/// Creates an `Add` instruction
+pub fn add(
+    program_id: &Pubkey,
+    calculator: &Pubkey,
+    authority: &Pubkey,
+    num1: u32,
+    num2: u32,
+) -> Instruction {
+    let data = CalculatorInstruction::Add { num1, num2 };
+    operation(program_id, calculator, authority, data)
+}
+
+/// Creates a `Subtract` instruction
+pub fn subtract(
+    program_id: &Pubkey,
+    calculator: &Pubkey,
+    authority: &Pubkey,
+    num1: u32,
+    num2: u32,
+) -> Instruction {
+    let data = CalculatorInstruction::Subtract { num1, num2 };
+    operation(program_id, calculator, authority, data)
+}
+
+/// Creates a `Multiply` instruction
+pub fn multiply(
+    program_id: &Pubkey,
+    calculator: &Pubkey,
+    authority: &Pubkey,
+    num1: u32,
+    num2: u32,
+) -> Instruction {
+    let data = CalculatorInstruction::Multiply { num1, num2 };
+    operation(program_id, calculator, authority, data)
+}
+
+/// Creates a `Divide` instruction
+pub fn divide(
+    program_id: &Pubkey,
+    calculator: &Pubkey,
+    authority: &Pubkey,
+    num1: u32,
+    num2: u32,
+) -> Instruction {
+    let data = CalculatorInstruction::Divide { num1, num2 };
+    operation(program_id, calculator, authority, data)
+}
+
+/// Creates a `Remainder` instruction
+pub fn remainder(
+    program_id: &Pubkey,
+    calculator: &Pubkey,
+    authority: &Pubkey,
+    num1: u32,
+    num2: u32,
+) -> Instruction {
+    let data = CalculatorInstruction::Remainder { num1, num2 };
+    operation(program_id, calculator, authority, data)
+}
+
+/// Creates a `SignedAdd` instruction
+pub fn signed_add(
+    program_id: &Pubkey,
+    calculator: &Pubkey,
+    authority: &Pubkey,
+    num1: i64,
+    num2: i64,
+) -> Instruction {
+    let data = CalculatorInstruction::SignedAdd { num1, num2 };
+    operation(program_id, calculator, authority, data)
+}
+
+/// Creates a `SignedSubtract` instruction
+pub fn signed_subtract(
+    program_id: &Pubkey,
+    calculator: &Pubkey,
+    authority: &Pubkey,
+    num1: i64,
+    num2: i64,
+) -> Instruction {
+    let data = CalculatorInstruction::SignedSubtract { num1, num2 };
+    operation(program_id, calculator, authority, data)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use solana_program::pubkey::MAX_SEED_LEN;
+
+    #[test]
+    fn test_pack_unpack_round_trip() {
+        let instructions = [
+            CalculatorInstruction::Add {
+                num1: 100,
+                num2: 30,
+            },
+            CalculatorInstruction::Subtract {
+                num1: u32::MAX,
+                num2: 1,
+            },
+            CalculatorInstruction::Multiply { num1: 6, num2: 7 },
+            CalculatorInstruction::Divide { num1: 7, num2: 2 },
+            CalculatorInstruction::Remainder { num1: 7, num2: 2 },
+            CalculatorInstruction::SignedAdd {
+                num1: -3,
+                num2: i64::MAX,
+            },
+            CalculatorInstruction::SignedSubtract { num1: 3, num2: 5 },
+            CalculatorInstruction::Initialize {
+                history_capacity: 16,
+            },
+            CalculatorInstruction::SetAuthority {
+                new_authority: Pubkey::new_unique(),
+            },
+            CalculatorInstruction::CreateCalculator {
+                history_capacity: 0,
+                label: String::new(),
+            },
+            // Encodes to the legacy length but not a legacy operation code
+            CalculatorInstruction::CreateCalculator {
+                history_capacity: 0,
+                label: "abc".to_string(),
+            },
+            CalculatorInstruction::Close,
+            CalculatorInstruction::MemoryAdd,
+            CalculatorInstruction::MemorySubtract,
+            CalculatorInstruction::MemoryRecall,
+            CalculatorInstruction::MemoryClear,
+            CalculatorInstruction::AccumulatorAdd { num: -5 },
+            CalculatorInstruction::AccumulatorSubtract { num: 5 },
+            CalculatorInstruction::AccumulatorMultiply { num: i64::MIN },
+            CalculatorInstruction::AccumulatorDivide { num: 2 },
+            CalculatorInstruction::AccumulatorSet { value: 0 },
+            CalculatorInstruction::Resize {
+                history_capacity: 64,
+            },
+            CalculatorInstruction::Migrate,
+            CalculatorInstruction::Arithmetic {
+                operation: Operation::Add,
+                operands: Operands::U32 { num1: 1, num2: 2 },
+            },
+            CalculatorInstruction::Arithmetic {
+                operation: Operation::Multiply,
+                operands: Operands::U64 {
+                    num1: u64::MAX,
+                    num2: 2,
+                },
+            },
+            CalculatorInstruction::Arithmetic {
+                operation: Operation::Remainder,
+                operands: Operands::U128 {
+                    num1: u128::MAX,
+                    num2: 7,
+                },
+            },
+            CalculatorInstruction::DecimalArithmetic {
+                operation: Operation::Divide,
+                num1: Decimal::new(1, 0),
+                num2: Decimal::new(3, 0),
+                scale: 18,
+                rounding: Rounding::Ceil,
+            },
+            CalculatorInstruction::DivideRounded {
+                operands: Operands::U64 { num1: 7, num2: 2 },
+                rounding: Rounding::HalfEven,
+            },
+            CalculatorInstruction::AccumulatorDivideRounded {
+                num: -2,
+                rounding: Rounding::HalfUp,
+            },
+            CalculatorInstruction::MulDiv {
+                operands: MulDivOperands::U128 {
+                    num1: u128::MAX,
+                    num2: 3,
+                    denominator: 4,
+                },
+                rounding: Rounding::Floor,
+            },
+            CalculatorInstruction::Pow {
+                base: Operand::U128(3),
+                exponent: 80,
+            },
+            CalculatorInstruction::Isqrt {
+                value: Operand::U64(u64::MAX),
+            },
+            CalculatorInstruction::NthRoot {
+                value: Operand::U128(1 << 90),
+                degree: 3,
+            },
+        ];
+
+        let mut tags = vec![];
+        for instruction in instructions {
+            let packed = instruction.pack();
+            assert_eq!(CalculatorInstruction::unpack(&packed).unwrap(), instruction);
+            tags.push(packed[0]);
+        }
+
+        // Every variant is covered, and is below the known tag count
+        tags.dedup();
+        assert_eq!(
+            tags,
+            (0..CalculatorInstruction::VARIANT_COUNT).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_unpack_routes_borsh_encodings_of_legacy_length() {
+        let operands = [
+            Operands::U32 { num1: 1, num2: 2 },
+            Operands::U64 { num1: 1, num2: 2 },
+            Operands::U128 { num1: 1, num2: 2 },
+        ];
+        let operand = [Operand::U64(1), Operand::U128(1)];
+        let mut instructions = vec![
+            CalculatorInstruction::Add { num1: 0, num2: 0 },
+            CalculatorInstruction::Subtract { num1: 0, num2: 0 },
+            CalculatorInstruction::Multiply { num1: 0, num2: 0 },
+            CalculatorInstruction::Divide { num1: 0, num2: 0 },
+            CalculatorInstruction::Remainder { num1: 0, num2: 0 },
+            CalculatorInstruction::SignedAdd { num1: 0, num2: 0 },
+            CalculatorInstruction::SignedSubtract { num1: 0, num2: 0 },
+            CalculatorInstruction::Initialize {
+                history_capacity: 0,
+            },
+            CalculatorInstruction::SetAuthority {
+                new_authority: Pubkey::default(),
+            },
+            CalculatorInstruction::Close,
+            CalculatorInstruction::MemoryAdd,
+            CalculatorInstruction::MemorySubtract,
+            CalculatorInstruction::MemoryRecall,
+            CalculatorInstruction::MemoryClear,
+            CalculatorInstruction::AccumulatorAdd { num: 0 },
+            CalculatorInstruction::AccumulatorSubtract { num: 0 },
+            CalculatorInstruction::AccumulatorMultiply { num: 0 },
+            CalculatorInstruction::AccumulatorDivide { num: 0 },
+            CalculatorInstruction::AccumulatorSet { value: 0 },
+            CalculatorInstruction::Resize {
+                history_capacity: 0,
+            },
+            CalculatorInstruction::Migrate,
+            CalculatorInstruction::DecimalArithmetic {
+                operation: Operation::Add,
+                num1: Decimal::new(0, 0),
+                num2: Decimal::new(0, 0),
+                scale: 0,
+                rounding: Rounding::Floor,
+            },
+            CalculatorInstruction::AccumulatorDivideRounded {
+                num: 0,
+                rounding: Rounding::Floor,
+            },
+            CalculatorInstruction::MulDiv {
+                operands: MulDivOperands::U64 {
+                    num1: 0,
+                    num2: 0,
+                    denominator: 1,
+                },
+                rounding: Rounding::Floor,
+            },
+            CalculatorInstruction::MulDiv {
+                operands: MulDivOperands::U128 {
+                    num1: 0,
+                    num2: 0,
+                    denominator: 1,
+                },
+                rounding: Rounding::Floor,
+            },
+        ];
+        for operands in operands {
+            instructions.push(CalculatorInstruction::Arithmetic {
+                operation: Operation::Add,
+                operands,
+            });
+            instructions.push(CalculatorInstruction::DivideRounded {
+                operands,
+                rounding: Rounding::Floor,
+            });
+        }
+        for value in operand {
+            instructions.push(CalculatorInstruction::Pow {
+                base: value,
+                exponent: 0,
+            });
+            instructions.push(CalculatorInstruction::Isqrt { value });
+            instructions.push(CalculatorInstruction::NthRoot { value, degree: 1 });
+        }
+        for len in 0..=MAX_SEED_LEN {
+            instructions.push(CalculatorInstruction::CreateCalculator {
+                history_capacity: 0,
+                label: "a".repeat(len),
+            });
+        }
+
+        // Every variant and operand width is listed, and `MulDiv` on `u128`
+        // operands is the longest
+        let longest = instructions.iter().map(|i| i.pack().len()).max();
+        assert_eq!(longest, Some(CalculatorInstruction::MAX_LEN));
+
+        // Only `CreateCalculator` with a 3-byte label encodes to the legacy length
+        let mut tags: Vec<_> = instructions.iter().map(|i| i.pack()[0]).collect();
+        tags.sort_unstable();
+        tags.dedup();
+        assert_eq!(
+            tags,
+            (0..CalculatorInstruction::VARIANT_COUNT).collect::<Vec<_>>()
+        );
+        let legacy_length: Vec<_> = instructions
+            .iter()
+            .filter(|i| i.pack().len() == LEGACY_INSTRUCTION_LEN)
+            .collect();
+        assert_eq!(
+            legacy_length,
+            [&CalculatorInstruction::CreateCalculator {
+                history_capacity: 0,
+                label: "aaa".to_string(),
+            }]
+        );
+
+        // Every 3-byte label decodes as Borsh, never through the legacy path.
+        // The capacity is not part of the legacy operation code.
+        let mut data = legacy_length[0].pack();
+        for label in 0..1u32 << 24 {
+            data[9..].copy_from_slice(&label.to_le_bytes()[..3]);
+            let Ok(label) = std::str::from_utf8(&data[9..]) else {
+                continue;
+            };
+            let expected = CalculatorInstruction::CreateCalculator {
+                history_capacity: 0,
+                label: label.to_string(),
+            };
+            assert_eq!(CalculatorInstruction::unpack(&data).unwrap(), expected);
+        }
+    }
+
+    // Legacy `[num1, num2, operation]` instruction data
+    fn legacy(num1: u32, num2: u32, operation: u32) -> Vec<u8> {
+        [
+            num1.to_le_bytes(),
+            num2.to_le_bytes(),
+            operation.to_le_bytes(),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn test_unpack_legacy_layout() {
+        assert_eq!(
+            CalculatorInstruction::unpack(&legacy(100, 30, 0)).unwrap(),
+            CalculatorInstruction::Add {
+                num1: 100,
+                num2: 30
+            }
+        );
+        assert_eq!(
+            CalculatorInstruction::unpack(&legacy(100, 30, 1)).unwrap(),
+            CalculatorInstruction::Subtract {
+                num1: 100,
+                num2: 30
+            }
+        );
+        // Other operation codes are not legacy payloads and fail Borsh decoding
+        assert_eq!(
+            CalculatorInstruction::unpack(&legacy(100, 30, 2)),
+            Err(CalculatorError::UnknownOperation.into())
+        );
+    }
+
+    #[test]
+    fn test_unpack_legacy_collisions() {
+        let nul_label = |history_capacity| CalculatorInstruction::CreateCalculator {
+            history_capacity,
+            label: "\0\0\0".to_string(),
+        };
+
+        // Labels of NUL bytes decode as Borsh whatever the capacity
+        for history_capacity in [0, 1, 0x00FF_FFFF, 0x0100_0000, u32::MAX] {
+            let data = nul_label(history_capacity).pack();
+            assert_eq!(data.len(), LEGACY_INSTRUCTION_LEN);
+            assert_eq!(data[8..], [0, 0, 0, 0]);
+            assert_eq!(
+                CalculatorInstruction::unpack(&data).unwrap(),
+                nul_label(history_capacity)
+            );
+        }
+
+        // Exactly the legacy additions of a `num1` of 9 modulo 256 and a
+        // `num2` in 0x300..=0x3FF are those encodings
+        for num1 in [9, 0x109, 0xFFFF_FF09] {
+            for num2 in [0x300, 0x3A5, 0x3FF] {
+                let capacity = (num1 >> 8) | (num2 & 0xFF) << 24;
+                assert_eq!(
+                    CalculatorInstruction::unpack(&legacy(num1, num2, 0)).unwrap(),
+                    nul_label(capacity)
+                );
+            }
+        }
+
+        // Their neighbours, and subtractions, stay legacy
+        for (num1, num2, operation) in [
+            (8, 0x300, 0),
+            (10, 0x300, 0),
+            (0x10A, 0x3FF, 0),
+            (9, 0x2FF, 0),
+            (9, 0x400, 0),
+            (9, 0x1_0300, 0),
+            (9, 0x300, 1),
+        ] {
+            let expected = if operation == 0 {
+                CalculatorInstruction::Add { num1, num2 }
+            } else {
+                CalculatorInstruction::Subtract { num1, num2 }
+            };
+            assert_eq!(
+                CalculatorInstruction::unpack(&legacy(num1, num2, operation)).unwrap(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_unpack_rejects_malformed_data() {
+        // Empty input
+        assert_eq!(
+            CalculatorInstruction::unpack(&[]),
+            Err(CalculatorError::InvalidInstruction.into())
+        );
+
+        // Unknown variant tag
+        assert_eq!(
+            CalculatorInstruction::unpack(&[0xff, 1, 0, 0, 0, 2, 0, 0, 0]),
+            Err(CalculatorError::UnknownOperation.into())
+        );
+
+        // A known tag with an unknown nested enum tag is malformed, not unknown
+        let mut packed = CalculatorInstruction::DivideRounded {
+            operands: Operands::U32 { num1: 7, num2: 2 },
+            rounding: Rounding::Floor,
+        }
+        .pack();
+        packed[1] = 9;
+        assert_eq!(
+            CalculatorInstruction::unpack(&packed),
+            Err(CalculatorError::InvalidInstruction.into())
+        );
+        *packed.last_mut().unwrap() = 4;
+        packed[1] = 0;
+        assert_eq!(
+            CalculatorInstruction::unpack(&packed),
+            Err(CalculatorError::InvalidInstruction.into())
+        );
+        assert_eq!(
+            CalculatorInstruction::unpack(&[22, 5, 0, 1, 0, 0, 0, 2, 0, 0, 0]),
+            Err(CalculatorError::InvalidInstruction.into())
+        );
+
+        // Truncated operands
+        let packed = CalculatorInstruction::Add { num1: 1, num2: 2 }.pack();
+        assert_eq!(
+            CalculatorInstruction::unpack(&packed[..packed.len() - 1]),
+            Err(CalculatorError::InvalidInstruction.into())
+        );
+
+        // Trailing bytes
+        let mut packed = CalculatorInstruction::Add { num1: 1, num2: 2 }.pack();
+        packed.push(0);
+        assert_eq!(
+            CalculatorInstruction::unpack(&packed),
+            Err(CalculatorError::InvalidInstruction.into())
+        );
+    }
+
+    #[test]
+    fn test_operation_result_layout() {
+        assert_eq!(
+            OperationResult::Unsigned(130).try_to_vec().unwrap(),
+            [0, 130, 0, 0, 0]
+        );
+        assert_eq!(
+            OperationResult::Signed(-2).try_to_vec().unwrap(),
+            [1, 0xfe, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]
+        );
+        assert_eq!(
+            OperationResult::U64(1 << 40).try_to_vec().unwrap(),
+            [2, 0, 0, 0, 0, 0, 1, 0, 0]
+        );
+        let packed = OperationResult::U128(u128::MAX).try_to_vec().unwrap();
+        assert_eq!(packed.len(), 1 + 16);
+        assert_eq!(packed[..2], [3, 0xff]);
+        let packed = OperationResult::Decimal(Decimal::new(u128::MAX, 18))
+            .try_to_vec()
+            .unwrap();
+        assert_eq!(packed.len(), OperationResult::MAX_LEN);
+        assert_eq!(packed[..2], [4, 0xff]);
+        assert_eq!(packed[17], 18);
+    }
+
+    #[test]
+    fn test_instruction_builders() {
+        let program_id = Pubkey::new_unique();
+        let calculator = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+
+        let instruction = add(&program_id, &calculator, &authority, 100, 30);
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(
+            instruction.data,
+            CalculatorInstruction::Add {
+                num1: 100,
+                num2: 30
+            }
+            .pack()
+        );
+        assert_eq!(
+            instruction.accounts,
+            [
+                AccountMeta::new(calculator, false),
+                AccountMeta::new_readonly(authority, true),
+            ]
+        );
+
+        let instruction = initialize(&program_id, &calculator, &authority, 4);
+        assert_eq!(
+            instruction.accounts,
+            [
+                AccountMeta::new(calculator, true),
+                AccountMeta::new_readonly(authority, true),
+            ]
+        );
+
+        let instruction = signed_subtract(&program_id, &calculator, &authority, 3, 5);
+        assert_eq!(
+            CalculatorInstruction::unpack(&instruction.data).unwrap(),
+            CalculatorInstruction::SignedSubtract { num1: 3, num2: 5 }
+        );
+
+        let operands = Operands::U128 {
+            num1: u128::MAX,
+            num2: 1,
+        };
+        let instruction = arithmetic(
+            &program_id,
+            &calculator,
+            &authority,
+            Operation::Subtract,
+            operands,
+        );
+        assert_eq!(instruction.data[..3], [22, 1, 2]);
+        assert_eq!(
+            CalculatorInstruction::unpack(&instruction.data).unwrap(),
+            CalculatorInstruction::Arithmetic {
+                operation: Operation::Subtract,
+                operands
+            }
+        );
+
+        let operands = MulDivOperands::U64 {
+            num1: 1_000,
+            num2: 30,
+            denominator: 10_000,
+        };
+        let instruction = mul_div(
+            &program_id,
+            &calculator,
+            &authority,
+            operands,
+            Rounding::Ceil,
+        );
+        assert_eq!(instruction.data.len(), 1 + 1 + 3 * 8 + 1);
+        assert_eq!(instruction.data[..3], [26, 0, 0xe8]);
+        assert_eq!(
+            CalculatorInstruction::unpack(&instruction.data).unwrap(),
+            CalculatorInstruction::MulDiv {
+                operands,
+                rounding: Rounding::Ceil
+            }
+        );
+
+        let instruction = nth_root(&program_id, &calculator, &authority, Operand::U64(27), 3);
+        assert_eq!(
+            instruction.data,
+            [[29, 0, 27].as_slice(), &[0; 7], &[3, 0, 0, 0]].concat()
+        );
+
+        let instruction = compute(
+            &program_id,
+            CalculatorInstruction::Multiply { num1: 6, num2: 7 },
+        );
+        assert!(instruction.accounts.is_empty());
+
+        let user = Pubkey::new_unique();
+        let instruction = create_calculator(&program_id, &user, "savings", 0);
+        assert_eq!(
+            instruction.accounts,
+            [
+                AccountMeta::new(
+                    find_calculator_address(&program_id, &user, "savings").0,
+                    false
+                ),
+                AccountMeta::new(user, true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ]
+        );
+
+        let payer = Pubkey::new_unique();
+        let instruction = migrate_v1(&program_id, &calculator, &authority, &payer);
+        assert_eq!(instruction.data, CalculatorInstruction::Migrate.pack());
+        assert_eq!(
+            instruction.accounts,
+            [
+                AccountMeta::new(calculator, true),
+                AccountMeta::new_readonly(authority, true),
+                AccountMeta::new(payer, true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ]
+        );
+
+        let destination = Pubkey::new_unique();
+        let instruction = close(&program_id, &calculator, &authority, &destination);
+        assert_eq!(instruction.data, CalculatorInstruction::Close.pack());
+        assert_eq!(
+            instruction.accounts,
+            [
+                AccountMeta::new(calculator, false),
+                AccountMeta::new_readonly(authority, true),
+                AccountMeta::new(destination, false),
+            ]
+        );
+    }
+}
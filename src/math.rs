@@ -0,0 +1,887 @@
+//! Integer arithmetic primitives shared by the calculator's operations
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use num_traits::{PrimInt, Unsigned};
+use std::cmp::Ordering;
+
+/// How a result that cannot be represented exactly is rounded
+///
+/// Borsh-encoded as a one byte tag in declaration order.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rounding {
+    /// Round down, toward negative infinity
+    Floor,
+    /// Round up, toward positive infinity
+    Ceil,
+    /// Round to the nearest result, with ties away from zero
+    HalfUp,
+    /// Round to the nearest result, with ties to the even one
+    HalfEven,
+}
+
+impl Rounding {
+    /// Whether an inexact result truncated toward zero must move one step
+    /// away from zero
+    ///
+    /// `negative` is the sign of the exact result, `remainder` compares the
+    /// discarded part with half a step and `odd` tells whether the truncated
+    /// result is odd.
+    pub fn rounds_away(self, negative: bool, remainder: Ordering, odd: bool) -> bool {
+        match self {
+            Self::Floor => negative,
+            Self::Ceil => !negative,
+            Self::HalfUp => remainder != Ordering::Less,
+            Self::HalfEven => {
+                remainder == Ordering::Greater || (remainder == Ordering::Equal && odd)
+            }
+        }
+    }
+}
+
+/// `num1 / num2` rounded as `rounding` says, or `None` if `num2` is zero or
+/// the quotient does not fit, which only `MIN / -1` of signed types does not
+pub fn div_rounded<T: PrimInt>(num1: T, num2: T, rounding: Rounding) -> Option<T> {
+    let quotient = num1.checked_div(&num2)?;
+    // Cannot overflow, the product is at most `num1` in magnitude
+    let remainder = num1 - quotient * num2;
+    if remainder.is_zero() {
+        return Some(quotient);
+    }
+
+    let zero = T::zero();
+    let one = T::one();
+    let abs = |value: T| if value < zero { zero - value } else { value };
+    // The exact quotient lies between the truncated one and the next integer
+    // away from zero, `|remainder|` and `|num2| - |remainder|` away from them
+    let negative = (remainder < zero) != (num2 < zero);
+    let to_next = if negative {
+        abs(num2 + remainder)
+    } else {
+        abs(num2 - remainder)
+    };
+    let odd = quotient % (one + one) != zero;
+
+    // The divisor is at least two, so the quotient is at most half the range
+    // and stepping away from zero cannot overflow
+    if rounding.rounds_away(negative, abs(remainder).cmp(&to_next), odd) {
+        Some(if negative {
+            quotient - one
+        } else {
+            quotient + one
+        })
+    } else {
+        Some(quotient)
+    }
+}
+
+/// `num1 * num2 / denominator` rounded as `rounding` says, or `None` if
+/// `denominator` is zero or the result does not fit a `u128`
+///
+/// The product is computed in full 256-bit width, so the result is exact
+/// whenever it fits, however large the product. Dividing takes a bounded loop
+/// of at most 128 shift-and-subtract steps.
+pub fn mul_div(num1: u128, num2: u128, denominator: u128, rounding: Rounding) -> Option<u128> {
+    let (quotient, remainder) = U256::mul(num1, num2).div_rem(denominator)?;
+    round_quotient(quotient, remainder, denominator, false, rounding)
+}
+
+// Largest power of ten that fits a `u128`
+const MAX_POW10_EXPONENT: u32 = 38;
+
+/// `num1 * num2 / 10^exponent` rounded as `rounding` says, or `None` if the
+/// result does not fit a `u128`
+///
+/// Like `mul_div`, but also exact when `10^exponent` itself exceeds a `u128`,
+/// as it does when rescaling the product of two decimals.
+pub fn mul_div_pow10(num1: u128, num2: u128, exponent: u32, rounding: Rounding) -> Option<u128> {
+    U256::mul(num1, num2).div_pow10(exponent, rounding)
+}
+
+/// `num * 10^exponent / denominator` rounded as `rounding` says, or `None` if
+/// `denominator` is zero or the result does not fit a `u128`
+///
+/// The scaled numerator is kept in 256 bits, so the result is exact whenever
+/// it fits, also when `10^exponent` itself exceeds a `u128`.
+pub fn mul_pow10_div(
+    num: u128,
+    exponent: u32,
+    denominator: u128,
+    rounding: Rounding,
+) -> Option<u128> {
+    if num == 0 {
+        return (denominator != 0).then_some(0);
+    }
+
+    let low_exponent = exponent.min(MAX_POW10_EXPONENT);
+    let mut numerator = U256::mul(num, 10u128.pow(low_exponent));
+    let mut exponent = exponent - low_exponent;
+    // A numerator beyond 256 bits divided by a `u128` leaves a quotient
+    // beyond 128 bits, so overflowing here means the result does not fit.
+    // That happens after at most seven steps.
+    while exponent > 0 {
+        let step = exponent.min(MAX_POW10_EXPONENT);
+        numerator = numerator.checked_mul(10u128.pow(step))?;
+        exponent -= step;
+    }
+
+    let (quotient, remainder) = numerator.div_rem(denominator)?;
+    round_quotient(quotient, remainder, denominator, false, rounding)
+}
+
+// Round a truncated unsigned quotient given its remainder, and whether any
+// digits divided away before it were non-zero
+fn round_quotient(
+    quotient: u128,
+    remainder: u128,
+    denominator: u128,
+    discarded: bool,
+    rounding: Rounding,
+) -> Option<u128> {
+    if remainder == 0 && !discarded {
+        return Some(quotient);
+    }
+
+    let half = match remainder.cmp(&(denominator - remainder)) {
+        Ordering::Equal if discarded => Ordering::Greater,
+        half => half,
+    };
+    if rounding.rounds_away(false, half, quotient % 2 == 1) {
+        quotient.checked_add(1)
+    } else {
+        Some(quotient)
+    }
+}
+
+/// `base` raised to `exponent`, or `None` if the power overflows; `0^0` is one
+///
+/// Exponentiation by squaring takes one step per bit of `exponent` and stops
+/// at the first overflow. Only zero, one and minus one have powers with an
+/// exponent of at least the bit width of `T`, so larger exponents are first
+/// reduced to the bit width, keeping their parity. That bounds the work at 13
+/// checked multiplications for 128-bit types and 11 for 64-bit types.
+pub fn checked_pow<T: PrimInt>(base: T, exponent: u32) -> Option<T> {
+    let bits = T::zero().count_zeros();
+    let mut result = T::one();
+    let mut square = base;
+    let mut exponent = if exponent >= bits {
+        bits + exponent % 2
+    } else {
+        exponent
+    };
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = checked_mul(result, square)?;
+        }
+        exponent >>= 1;
+        // Only square when a higher bit still needs it, the result would
+        // overflow if the square did
+        if exponent > 0 {
+            square = checked_mul(square, square)?;
+        }
+    }
+    Some(result)
+}
+
+// `num1 * num2`, counted in tests to check the bounds documented above
+fn checked_mul<T: PrimInt>(num1: T, num2: T) -> Option<T> {
+    #[cfg(test)]
+    test::MULTIPLICATIONS.with(|count| count.set(count.get() + 1));
+    num1.checked_mul(&num2)
+}
+
+/// The integer square root, the largest `root` with `root * root <= value`
+///
+/// Computed a bit of the root at a time without any multiplication, in at
+/// most half as many steps as `T` has bits, 64 for `u128`.
+pub fn isqrt<T: PrimInt + Unsigned>(value: T) -> T {
+    let bits = T::zero().count_zeros() as usize;
+    // Start from the highest power of four not above `value`
+    let mut bit = T::one() << (bits - 2);
+    while bit > value {
+        bit = bit >> 2;
+    }
+
+    let mut remainder = value;
+    let mut root = T::zero();
+    while !bit.is_zero() {
+        if remainder >= root + bit {
+            remainder = remainder - (root + bit);
+            root = (root >> 1) + bit;
+        } else {
+            root = root >> 1;
+        }
+        bit = bit >> 2;
+    }
+    root
+}
+
+/// The integer `degree`-th root, the largest `root` with
+/// `root^degree <= value`, or `None` if `degree` is zero
+///
+/// The root of a `T` has at most `bits / degree` bits, rounded up, and each
+/// is found with one `checked_pow`. Degrees of at least the bit width of `T`
+/// need no work and square roots use `isqrt`, so a cube root is the worst
+/// case: 129 checked multiplications for `u128`, 66 for `u64`.
+pub fn nth_root<T: PrimInt + Unsigned>(value: T, degree: u32) -> Option<T> {
+    let bits = T::zero().count_zeros();
+    match degree {
+        0 => None,
+        1 => Some(value),
+        2 => Some(isqrt(value)),
+        // Two to the power of `degree` already exceeds `T`
+        _ if degree >= bits => Some(value.min(T::one())),
+        _ => {
+            let mut root = T::zero();
+            for bit in (0..(bits + degree - 1) / degree).rev() {
+                let candidate = root | (T::one() << bit as usize);
+                if checked_pow(candidate, degree).map_or(false, |power| power <= value) {
+                    root = candidate;
+                }
+            }
+            Some(root)
+        }
+    }
+}
+
+// An unsigned 256-bit integer as its high and low 128-bit halves, ordered by
+// the high half first
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct U256 {
+    pub(crate) high: u128,
+    pub(crate) low: u128,
+}
+
+impl U256 {
+    pub(crate) const ZERO: Self = Self { high: 0, low: 0 };
+
+    // The full product of two `u128`s, from the products of their 64-bit halves
+    pub(crate) fn mul(num1: u128, num2: u128) -> Self {
+        const MASK: u128 = u64::MAX as u128;
+        let (high1, low1) = (num1 >> 64, num1 & MASK);
+        let (high2, low2) = (num2 >> 64, num2 & MASK);
+
+        let low_low = low1 * low2;
+        let high_low = high1 * low2;
+        let low_high = low1 * high2;
+        let high_high = high1 * high2;
+
+        // Sum the middle column with the carry out of the lowest one; at most
+        // three 64-bit values, so it cannot overflow
+        let middle = (low_low >> 64) + (high_low & MASK) + (low_high & MASK);
+        Self {
+            high: high_high + (high_low >> 64) + (low_high >> 64) + (middle >> 64),
+            low: (middle << 64) | (low_low & MASK),
+        }
+    }
+
+    // `self + other`, or `None` if the sum exceeds 256 bits
+    pub(crate) fn checked_add(self, other: Self) -> Option<Self> {
+        let (low, carry) = self.low.overflowing_add(other.low);
+        let high = self
+            .high
+            .checked_add(other.high)?
+            .checked_add(carry.into())?;
+        Some(Self { high, low })
+    }
+
+    // `self - other`, or `None` if `other` is larger
+    pub(crate) fn checked_sub(self, other: Self) -> Option<Self> {
+        let (low, borrow) = self.low.overflowing_sub(other.low);
+        let high = self
+            .high
+            .checked_sub(other.high)?
+            .checked_sub(borrow.into())?;
+        Some(Self { high, low })
+    }
+
+    // Multiply by `factor`, or `None` if the product exceeds 256 bits
+    pub(crate) fn checked_mul(self, factor: u128) -> Option<Self> {
+        let low = Self::mul(self.low, factor);
+        let high = Self::mul(self.high, factor);
+        if high.high != 0 {
+            return None;
+        }
+        let (high, carry) = low.high.overflowing_add(high.low);
+        (!carry).then_some(Self { high, low: low.low })
+    }
+
+    // Divide by `divisor`, returning the quotient and remainder, or `None` if
+    // the divisor is zero or the quotient does not fit a `u128`
+    pub(crate) fn div_rem(self, divisor: u128) -> Option<(u128, u128)> {
+        if self.high >= divisor {
+            return None;
+        }
+        if self.high == 0 {
+            return Some((self.low / divisor, self.low % divisor));
+        }
+
+        // Restoring long division, shifting the low half into the remainder a
+        // bit at a time; the remainder stays below the divisor, so with the
+        // bit shifted out of it, it always fits
+        let mut remainder = self.high;
+        let mut quotient = 0;
+        for bit in (0..128).rev() {
+            let carry = remainder >> 127;
+            remainder = (remainder << 1) | ((self.low >> bit) & 1);
+            if carry != 0 || remainder >= divisor {
+                remainder = remainder.wrapping_sub(divisor);
+                quotient |= 1 << bit;
+            }
+        }
+        Some((quotient, remainder))
+    }
+
+    // Divide by `10^exponent` rounded as `rounding` says, or `None` if the
+    // quotient does not fit a `u128`
+    pub(crate) fn div_pow10(self, exponent: u32, rounding: Rounding) -> Option<u128> {
+        let mut numerator = self;
+        let mut exponent = exponent;
+        let mut discarded = false;
+        // Divide by `10^38` until the rest of the power fits; what remains to
+        // divide by is still a multiple of ten, so the digits divided away only
+        // break ties. A 256-bit numerator is zero after at most seven steps.
+        while exponent > MAX_POW10_EXPONENT && numerator != Self::ZERO {
+            let (quotient, remainder) = numerator.div_rem_wide(10u128.pow(MAX_POW10_EXPONENT));
+            numerator = quotient;
+            discarded |= remainder != 0;
+            exponent -= MAX_POW10_EXPONENT;
+        }
+
+        let denominator = 10u128.pow(exponent.min(MAX_POW10_EXPONENT));
+        let (quotient, remainder) = numerator.div_rem(denominator)?;
+        round_quotient(quotient, remainder, denominator, discarded, rounding)
+    }
+
+    // Divide by a non-zero `divisor`, returning the 256-bit quotient and the
+    // remainder
+    fn div_rem_wide(self, divisor: u128) -> (Self, u128) {
+        let high = Self {
+            high: 0,
+            low: self.high,
+        };
+        let (quotient_high, remainder) = high.div_rem(divisor).unwrap();
+        // The remainder is below the divisor, so the quotient fits
+        let low = Self {
+            high: remainder,
+            low: self.low,
+        };
+        let (quotient_low, remainder) = low.div_rem(divisor).unwrap();
+        (
+            Self {
+                high: quotient_high,
+                low: quotient_low,
+            },
+            remainder,
+        )
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use std::cell::Cell;
+
+    pub const ROUNDINGS: [Rounding; 4] = [
+        Rounding::Floor,
+        Rounding::Ceil,
+        Rounding::HalfUp,
+        Rounding::HalfEven,
+    ];
+
+    /// `num1 - num2` for `num1 >= num2`
+    pub(crate) fn sub_wide(num1: U256, num2: U256) -> U256 {
+        let (low, borrow) = num1.low.overflowing_sub(num2.low);
+        U256 {
+            high: num1.high - num2.high - u128::from(borrow),
+            low,
+        }
+    }
+
+    /// `10^exponent` for exponents up to 76
+    pub(crate) fn pow10_wide(exponent: u32) -> U256 {
+        let low_exponent = exponent.min(MAX_POW10_EXPONENT);
+        let power = U256 {
+            high: 0,
+            low: 10u128.pow(low_exponent),
+        };
+        power
+            .checked_mul(10u128.pow(exponent - low_exponent))
+            .unwrap()
+    }
+
+    /// Reference rounding of `num1 * num2 / 10^exponent`, for exponents up
+    /// to 76
+    pub fn reference_mul_div_pow10(
+        num1: u128,
+        num2: u128,
+        exponent: u32,
+        rounding: Rounding,
+    ) -> Option<u128> {
+        reference_div_wide(U256::mul(num1, num2), pow10_wide(exponent), rounding)
+    }
+
+    /// Reference rounding of `numerator / denominator` from the full
+    /// remainder, or `None` if the result does not fit a `u128`
+    pub(crate) fn reference_div_wide(
+        numerator: U256,
+        denominator: U256,
+        rounding: Rounding,
+    ) -> Option<u128> {
+        // Binary search for the truncated quotient
+        let (mut low, mut high) = (0u128, u128::MAX);
+        let fits =
+            |candidate| matches!(denominator.checked_mul(candidate), Some(n) if n <= numerator);
+        while low < high {
+            let middle = high - (high - low) / 2;
+            if fits(middle) {
+                low = middle;
+            } else {
+                high = middle - 1;
+            }
+        }
+        let remainder = sub_wide(numerator, denominator.checked_mul(low).unwrap());
+        if remainder >= denominator {
+            return None;
+        }
+        if remainder == U256::ZERO {
+            return Some(low);
+        }
+        let half = remainder.cmp(&sub_wide(denominator, remainder));
+        if rounding.rounds_away(false, half, low % 2 == 1) {
+            low.checked_add(1)
+        } else {
+            Some(low)
+        }
+    }
+
+    /// Reference rounding of the exact quotient `num1 / num2`: search the
+    /// integers around it for the one each mode picks by definition
+    pub fn reference_div(num1: i128, num2: i128, rounding: Rounding) -> i128 {
+        // Normalize to a positive divisor
+        let (num1, num2) = if num2 < 0 {
+            (-num1, -num2)
+        } else {
+            (num1, num2)
+        };
+        let candidates = (num1 / num2 - 1)..=(num1 / num2 + 1);
+        // Twice the distance of `candidate` from the exact quotient, scaled by `num2`
+        let distance = |candidate: i128| (2 * (candidate * num2 - num1)).abs();
+        match rounding {
+            Rounding::Floor => candidates.filter(|c| c * num2 <= num1).max(),
+            Rounding::Ceil => candidates.filter(|c| c * num2 >= num1).min(),
+            Rounding::HalfUp => candidates.min_by_key(|&c| (distance(c), -c.abs())),
+            Rounding::HalfEven => candidates.min_by_key(|&c| (distance(c), c.rem_euclid(2))),
+        }
+        .unwrap()
+    }
+
+    #[test]
+    fn test_div_rounded_unsigned() {
+        for rounding in ROUNDINGS {
+            for num1 in u8::MIN..=u8::MAX {
+                assert_eq!(div_rounded(num1, 0, rounding), None);
+                for num2 in 1..=u8::MAX {
+                    let expected = reference_div(num1.into(), num2.into(), rounding);
+                    assert_eq!(
+                        div_rounded(num1, num2, rounding).map(i128::from),
+                        Some(expected),
+                        "{} / {} {:?}",
+                        num1,
+                        num2,
+                        rounding
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_div_rounded_signed() {
+        for rounding in ROUNDINGS {
+            for num1 in i8::MIN..=i8::MAX {
+                assert_eq!(div_rounded(num1, 0, rounding), None);
+                for num2 in (i8::MIN..=i8::MAX).filter(|&num2| num2 != 0) {
+                    let expected = reference_div(num1.into(), num2.into(), rounding);
+                    // Only `i8::MIN / -1` is out of range
+                    let expected = i8::try_from(expected).ok();
+                    assert_eq!(
+                        div_rounded(num1, num2, rounding),
+                        expected,
+                        "{} / {} {:?}",
+                        num1,
+                        num2,
+                        rounding
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_mul_div() {
+        // Small operands against the reference
+        for rounding in ROUNDINGS {
+            for num1 in 0..24 {
+                for num2 in 0..24 {
+                    assert_eq!(mul_div(num1, num2, 0, rounding), None);
+                    for denominator in 1..24 {
+                        let expected =
+                            reference_div((num1 * num2) as i128, denominator as i128, rounding);
+                        assert_eq!(
+                            mul_div(num1, num2, denominator, rounding),
+                            Some(expected as u128)
+                        );
+                    }
+                }
+            }
+        }
+
+        // Products far beyond 128 bits are exact when the result fits
+        let max = u128::MAX;
+        assert_eq!(mul_div(max, max, max, Rounding::Floor), Some(max));
+        assert_eq!(mul_div(max, 3, 3, Rounding::Ceil), Some(max));
+        assert_eq!(mul_div(max, max - 1, max, Rounding::Floor), Some(max - 1));
+        assert_eq!(mul_div(1 << 127, 8, 4, Rounding::Floor), None);
+        assert_eq!(mul_div(max, max, 1, Rounding::Floor), None);
+        // Pro-rata share of a pool: 1/3 of u128::MAX, rounded each way
+        assert_eq!(mul_div(max, 1, 3, Rounding::Floor), Some(max / 3));
+        assert_eq!(mul_div(max, 2, 6, Rounding::Floor), Some(max / 3));
+        assert_eq!(mul_div(max - 1, 2, 6, Rounding::Floor), Some(max / 3 - 1));
+        assert_eq!(mul_div(max - 1, 2, 6, Rounding::Ceil), Some(max / 3));
+        assert_eq!(mul_div(max - 1, 2, 6, Rounding::HalfUp), Some(max / 3));
+        // Rounding up past the largest result overflows
+        assert_eq!(mul_div(max, 2, 2, Rounding::Ceil), Some(max));
+        assert_eq!(mul_div(max, 4, 3, Rounding::Ceil), None);
+        assert_eq!(
+            mul_div(max - 1, max, max - 1, Rounding::HalfEven),
+            Some(max)
+        );
+    }
+
+    #[test]
+    fn test_mul_div_pow10() {
+        // Powers of ten that fit agree with `mul_div`
+        for rounding in ROUNDINGS {
+            for exponent in 0..=MAX_POW10_EXPONENT {
+                let denominator = 10u128.pow(exponent);
+                for (num1, num2) in [(7, 13), (u128::MAX, 3), (u128::MAX, u128::MAX)] {
+                    assert_eq!(
+                        mul_div_pow10(num1, num2, exponent, rounding),
+                        mul_div(num1, num2, denominator, rounding)
+                    );
+                }
+            }
+        }
+
+        // Larger powers divide exactly in steps
+        let max = u128::MAX;
+        let e38 = 10u128.pow(38);
+        assert_eq!(mul_div_pow10(e38, e38, 76, Rounding::Floor), Some(1));
+        assert_eq!(mul_div_pow10(e38, e38, 75, Rounding::Floor), Some(10));
+        assert_eq!(
+            mul_div_pow10(e38 / 2 * 3, e38, 76, Rounding::HalfEven),
+            Some(2)
+        );
+        assert_eq!(
+            mul_div_pow10(e38 / 2 * 3, e38, 76, Rounding::HalfUp),
+            Some(2)
+        );
+        assert_eq!(
+            mul_div_pow10(e38 / 2 * 5, e38, 76, Rounding::HalfEven),
+            Some(2)
+        );
+        assert_eq!(
+            mul_div_pow10(e38 / 2 * 5, e38, 76, Rounding::HalfUp),
+            Some(3)
+        );
+        // Half a unit plus a digit far below the tie rounds up
+        assert_eq!(
+            mul_div_pow10(e38 / 2 * 5 + 1, e38, 76, Rounding::HalfEven),
+            Some(3)
+        );
+        assert_eq!(mul_div_pow10(1, 1, 76, Rounding::Ceil), Some(1));
+        assert_eq!(mul_div_pow10(1, 1, 76, Rounding::HalfUp), Some(0));
+        assert_eq!(mul_div_pow10(max, max, 76, Rounding::Floor), Some(11));
+        assert_eq!(mul_div_pow10(max, max, 77, Rounding::Floor), Some(1));
+        assert_eq!(mul_div_pow10(max, max, u32::MAX, Rounding::Ceil), Some(1));
+        assert_eq!(mul_div_pow10(0, max, u32::MAX, Rounding::Ceil), Some(0));
+        assert_eq!(mul_div_pow10(max, max, 0, Rounding::Floor), None);
+
+        // Pseudo-random operands against the full remainder
+        let mut seed = 0x9e37_79b9_7f4a_7c15_u128;
+        let mut next = || {
+            seed = seed
+                .wrapping_mul(0x2360_ed05_1fc6_5da4_4385_df64_9fcc_f645)
+                .wrapping_add(0x5851_f42d_4c95_7f2d_1405_7b7e_f767_814f);
+            seed
+        };
+        for _ in 0..2_000 {
+            let (num1, num2) = (next() >> (next() % 128), next() >> (next() % 128));
+            let exponent = (next() % 77) as u32;
+            for rounding in ROUNDINGS {
+                assert_eq!(
+                    mul_div_pow10(num1, num2, exponent, rounding),
+                    reference_mul_div_pow10(num1, num2, exponent, rounding),
+                    "{} * {} / 10^{} {:?}",
+                    num1,
+                    num2,
+                    exponent,
+                    rounding
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_mul_pow10_div() {
+        let e38 = 10u128.pow(38);
+        assert_eq!(
+            mul_pow10_div(1, 58, 10u128.pow(20), Rounding::Floor),
+            Some(e38)
+        );
+        assert_eq!(
+            mul_pow10_div(2, 76, 3 * e38, Rounding::Floor),
+            Some(e38 / 3 * 2)
+        );
+        assert_eq!(
+            mul_pow10_div(2, 76, 3 * e38, Rounding::Ceil),
+            Some(e38 / 3 * 2 + 1)
+        );
+        assert_eq!(mul_pow10_div(1, 39, 1, Rounding::Floor), None);
+        assert_eq!(
+            mul_pow10_div(u128::MAX, 200, u128::MAX, Rounding::Floor),
+            None
+        );
+        assert_eq!(mul_pow10_div(0, u32::MAX, 1, Rounding::Floor), Some(0));
+        assert_eq!(mul_pow10_div(0, 1, 0, Rounding::Floor), None);
+        assert_eq!(mul_pow10_div(1, 1, 0, Rounding::Floor), None);
+
+        // Pseudo-random operands against the full remainder
+        let mut seed = 0x6a09_e667_f3bc_c908_u128;
+        let mut next = || {
+            seed = seed
+                .wrapping_mul(0x2360_ed05_1fc6_5da4_4385_df64_9fcc_f645)
+                .wrapping_add(0x5851_f42d_4c95_7f2d_1405_7b7e_f767_814f);
+            seed
+        };
+        for _ in 0..2_000 {
+            let num = next() >> (next() % 128);
+            let denominator = (next() >> (next() % 128)).max(1);
+            let exponent = (next() % 77) as u32;
+            let denominator_wide = U256 {
+                high: 0,
+                low: denominator,
+            };
+            for rounding in ROUNDINGS {
+                // A numerator beyond 256 bits over a `u128` cannot fit
+                let expected = pow10_wide(exponent).checked_mul(num).and_then(|numerator| {
+                    reference_div_wide(numerator, denominator_wide, rounding)
+                });
+                assert_eq!(
+                    mul_pow10_div(num, exponent, denominator, rounding),
+                    expected,
+                    "{} * 10^{} / {} {:?}",
+                    num,
+                    exponent,
+                    denominator,
+                    rounding
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_u256_matches_identity() {
+        // Pseudo-random operands of every magnitude satisfy
+        // `num1 * num2 == quotient * denominator + remainder`
+        let mut seed = 0x2545_f491_4f6c_dd1d_u128;
+        let mut next = || {
+            seed = seed
+                .wrapping_mul(0x2360_ed05_1fc6_5da4_4385_df64_9fcc_f645)
+                .wrapping_add(0x5851_f42d_4c95_7f2d_1405_7b7e_f767_814f);
+            seed >> (seed % 128)
+        };
+        for _ in 0..10_000 {
+            let (num1, num2, denominator) = (next(), next(), next().max(1));
+            let product = U256::mul(num1, num2);
+            if let Some((quotient, remainder)) = product.div_rem(denominator) {
+                assert!(remainder < denominator);
+                let back = U256::mul(quotient, denominator);
+                let (low, carry) = back.low.overflowing_add(remainder);
+                assert_eq!(
+                    U256 {
+                        high: back.high + u128::from(carry),
+                        low
+                    },
+                    product
+                );
+            } else {
+                assert!(product.high >= denominator);
+            }
+
+            // Adding and subtracting are inverses, and fail past 256 bits
+            // or below zero
+            let other = U256::mul(num2, denominator);
+            match product.checked_add(other) {
+                Some(sum) => {
+                    assert_eq!(sum.checked_sub(other), Some(product));
+                    assert_eq!(sum.checked_sub(product), Some(other));
+                }
+                None => assert!(product.high.checked_add(other.high).map_or(true, |high| {
+                    high == u128::MAX && product.low.checked_add(other.low).is_none()
+                })),
+            }
+            if product < other {
+                assert_eq!(product.checked_sub(other), None);
+            }
+        }
+    }
+
+    #[test]
+    fn test_checked_pow() {
+        // Every base and exponent of the small types against a wide power
+        for exponent in 0..20 {
+            for base in u8::MIN..=u8::MAX {
+                let expected = i128::from(base).checked_pow(exponent);
+                let expected = expected.and_then(|power| u8::try_from(power).ok());
+                assert_eq!(checked_pow(base, exponent), expected);
+            }
+            for base in i8::MIN..=i8::MAX {
+                let expected = i128::from(base).checked_pow(exponent);
+                let expected = expected.and_then(|power| i8::try_from(power).ok());
+                assert_eq!(
+                    checked_pow(base, exponent),
+                    expected,
+                    "{}^{}",
+                    base,
+                    exponent
+                );
+            }
+        }
+
+        assert_eq!(checked_pow(0u32, 0), Some(1));
+        assert_eq!(checked_pow(0u32, u32::MAX), Some(0));
+        assert_eq!(checked_pow(1u128, u32::MAX), Some(1));
+        assert_eq!(checked_pow(2u128, 127), Some(1 << 127));
+        assert_eq!(checked_pow(2u128, 128), None);
+        assert_eq!(checked_pow(10u64, 19), Some(10u64.pow(19)));
+        assert_eq!(checked_pow(10u64, 20), None);
+        assert_eq!(checked_pow(u128::MAX, 1), Some(u128::MAX));
+
+        // Exponents past the bit width keep their parity
+        assert_eq!(checked_pow(-1i8, u32::MAX), Some(-1));
+        assert_eq!(checked_pow(-1i8, u32::MAX - 1), Some(1));
+        assert_eq!(checked_pow(1u8, u32::MAX), Some(1));
+        assert_eq!(checked_pow(2u8, u32::MAX), None);
+        assert_eq!(checked_pow(i128::MIN, 128), None);
+    }
+
+    #[test]
+    fn test_isqrt() {
+        for value in u16::MIN..=u16::MAX {
+            let root = isqrt(value);
+            let (root, value) = (u32::from(root), u32::from(value));
+            assert!(root * root <= value && (root + 1) * (root + 1) > value);
+        }
+
+        assert_eq!(isqrt(0u128), 0);
+        assert_eq!(isqrt(u64::MAX), u64::from(u32::MAX));
+        assert_eq!(isqrt(u128::MAX), u128::from(u64::MAX));
+        let root = u128::from(u64::MAX);
+        assert_eq!(isqrt(root * root), root);
+        assert_eq!(isqrt(root * root - 1), root - 1);
+    }
+
+    #[test]
+    fn test_nth_root() {
+        for degree in 1..20 {
+            for value in u16::MIN..=u16::MAX {
+                let root = nth_root(value, degree).unwrap();
+                let (root, value) = (u128::from(root), u128::from(value));
+                assert!(root.pow(degree) <= value);
+                assert!((root + 1).pow(degree) > value, "{} {}", value, degree);
+            }
+        }
+
+        assert_eq!(nth_root(8u64, 0), None);
+        assert_eq!(nth_root(u128::MAX, 3), Some(6_981_463_658_331));
+        assert_eq!(nth_root(u128::MAX, 127), Some(2));
+        assert_eq!(nth_root(u128::MAX, 128), Some(1));
+        assert_eq!(nth_root(0u128, u32::MAX), Some(0));
+        let cube = 6_981_463_658_331u128.pow(3);
+        assert_eq!(nth_root(cube, 3), Some(6_981_463_658_331));
+        assert_eq!(nth_root(cube - 1, 3), Some(6_981_463_658_330));
+        assert_eq!(nth_root(10u64.pow(18), 6), Some(1_000));
+        assert_eq!(nth_root(10u64.pow(18) - 1, 6), Some(999));
+    }
+
+    thread_local! {
+        // Checked multiplications made by `checked_pow` on this test thread
+        pub static MULTIPLICATIONS: Cell<u32> = const { Cell::new(0) };
+    }
+
+    fn count_multiplications<R>(f: impl FnOnce() -> R) -> (R, u32) {
+        MULTIPLICATIONS.with(|count| count.set(0));
+        let result = f();
+        (result, MULTIPLICATIONS.with(Cell::get))
+    }
+
+    #[test]
+    fn test_worst_case_multiplications() {
+        // Every exponent bit multiplies the result and all but the highest
+        // square the base, so seven bits set cost the most, and only zero and
+        // one stay in range for all of them
+        for base in [0u128, 1] {
+            let (power, count) = count_multiplications(|| checked_pow(base, 127));
+            assert_eq!(power, Some(base));
+            assert_eq!(count, 13);
+        }
+        let (_, count) = count_multiplications(|| checked_pow(1u64, 63));
+        assert_eq!(count, 11);
+        for exponent in (0..=300).chain(u32::MAX - 300..=u32::MAX) {
+            for base in [0, 1, 2, 3, u128::MAX] {
+                let (_, count) = count_multiplications(|| checked_pow(base, exponent));
+                assert!(count <= 13, "{}^{} {}", base, exponent, count);
+                let base = base as u64;
+                let (_, count) = count_multiplications(|| checked_pow(base, exponent));
+                assert!(count <= 11, "{}^{} {}", base, exponent, count);
+            }
+        }
+
+        // A cube root needs a cube for each of its 43 bits
+        let (root, count) = count_multiplications(|| nth_root(u128::MAX, 3));
+        assert_eq!(root, Some(6_981_463_658_331));
+        assert_eq!(count, 129);
+        let (_, count) = count_multiplications(|| nth_root(u64::MAX, 3));
+        assert_eq!(count, 66);
+        for degree in 1..=130 {
+            for value in [0, 1, u128::from(u64::MAX), u128::MAX] {
+                let (_, count) = count_multiplications(|| nth_root(value, degree));
+                assert!(count <= 129, "{} {} {}", value, degree, count);
+            }
+        }
+    }
+
+    #[test]
+    fn test_rounding_examples() {
+        let cases = [
+            (7, 2, [3, 4, 4, 4]),
+            (5, 2, [2, 3, 3, 2]),
+            (-5, 2, [-3, -2, -3, -2]),
+            (-7, 2, [-4, -3, -4, -4]),
+            (7, -3, [-3, -2, -2, -2]),
+            (8, 3, [2, 3, 3, 3]),
+            (6, 3, [2, 2, 2, 2]),
+        ];
+        for (num1, num2, expected) in cases {
+            for (rounding, expected) in ROUNDINGS.into_iter().zip(expected) {
+                assert_eq!(div_rounded(num1, num2, rounding), Some(expected));
+            }
+        }
+        assert_eq!(div_rounded(i64::MIN, -1, Rounding::Floor), None);
+        assert_eq!(
+            div_rounded(u128::MAX, 2, Rounding::HalfUp),
+            Some(u128::MAX / 2 + 1)
+        );
+    }
+}
@@ -0,0 +1,52 @@
+//! Integer types with an alignment of one for zero-copy views of account data
+//!
+//! Each type stores its value as little-endian bytes, matching the Borsh
+//! encoding, so a `#[repr(C)]` struct of them overlays Borsh-encoded data.
+
+use bytemuck::{Pod, Zeroable};
+
+macro_rules! pod_int {
+    ($name:ident, $int:ty) => {
+        #[doc = concat!("A little-endian `", stringify!($int), "` stored as bytes")]
+        #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Pod, Zeroable)]
+        #[repr(transparent)]
+        pub struct $name(pub [u8; std::mem::size_of::<$int>()]);
+
+        impl From<$int> for $name {
+            fn from(value: $int) -> Self {
+                Self(value.to_le_bytes())
+            }
+        }
+
+        impl From<$name> for $int {
+            fn from(value: $name) -> Self {
+                <$int>::from_le_bytes(value.0)
+            }
+        }
+    };
+}
+
+pod_int!(PodU32, u32);
+pod_int!(PodU64, u64);
+pod_int!(PodU128, u128);
+pod_int!(PodI64, i64);
+
+/// A `bool` stored as a byte
+///
+/// Borsh only decodes 0 and 1, so validate data against `PodBool::from(true)`
+/// rather than converting bytes that were never checked.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Pod, Zeroable)]
+#[repr(transparent)]
+pub struct PodBool(pub u8);
+
+impl From<bool> for PodBool {
+    fn from(value: bool) -> Self {
+        Self(value.into())
+    }
+}
+
+impl From<PodBool> for bool {
+    fn from(value: PodBool) -> Self {
+        value.0 != 0
+    }
+}
@@ -0,0 +1,2783 @@
+use crate::{
+    error::CalculatorError,
+    instruction::{
+        CalculatorInstruction, MulDivOperands, Operand, Operands, Operation, OperationResult,
+    },
+    math::{checked_pow, div_rounded, isqrt, mul_div, nth_root, Rounding},
+    state::{
+        find_calculator_address, CalcResult, HistoryEntry, PodCalcResult, VersionedCalcResult,
+        CALCULATOR_SEED,
+    },
+};
+use borsh::BorshSerialize;
+use bytemuck::Zeroable;
+use num_traits::{CheckedRem, PrimInt};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::{ProgramResult, MAX_PERMITTED_DATA_INCREASE},
+    msg,
+    program::{invoke, invoke_signed, set_return_data},
+    program_error::ProgramError,
+    pubkey::{Pubkey, MAX_SEED_LEN},
+    rent::Rent,
+    system_instruction, system_program,
+    sysvar::Sysvar,
+};
+use std::{fmt::Display, slice::Iter};
+
+// Program entrypoint's implementation
+pub fn handle_instruction(
+    program_id: &Pubkey, // Public key of the account the calculator program was loaded into
+    accounts: &[AccountInfo], // Accounts used by the program
+    instruction_data: &[u8], // Borsh-encoded `CalculatorInstruction` or the legacy 12-byte layout
+) -> ProgramResult {
+    msg!("Calculator program entrypoint");
+
+    // Parse the input data
+    let instruction = CalculatorInstruction::unpack(instruction_data)?;
+
+    match instruction {
+        CalculatorInstruction::Initialize { history_capacity } => {
+            process_initialize(program_id, accounts, history_capacity)
+        }
+        CalculatorInstruction::CreateCalculator {
+            history_capacity,
+            label,
+        } => process_create_calculator(program_id, accounts, &label, history_capacity),
+        CalculatorInstruction::Close => process_close(program_id, accounts),
+        CalculatorInstruction::Resize { history_capacity } => {
+            process_resize(program_id, accounts, history_capacity)
+        }
+        CalculatorInstruction::Migrate => process_migrate(program_id, accounts),
+        CalculatorInstruction::SetAuthority { new_authority } => {
+            update_state(program_id, accounts, |calc_data, _| {
+                calc_data.authority = new_authority;
+                msg!("Calculator authority set to {}", new_authority);
+                Ok(())
+            })
+        }
+        CalculatorInstruction::MemoryAdd => {
+            process_memory(program_id, accounts, &instruction, |calc_data| {
+                let last_result = i64::from(calc_data.last_result);
+                let memory = i64::from(calc_data.memory)
+                    .checked_add(last_result)
+                    .ok_or_else(|| {
+                        msg!("Invalid memory addition: memory is out of range");
+                        if last_result > 0 {
+                            CalculatorError::Overflow
+                        } else {
+                            CalculatorError::Underflow
+                        }
+                    })?;
+                calc_data.memory = memory.into();
+                msg!("Memory: {}", memory);
+                Ok(())
+            })
+        }
+        CalculatorInstruction::MemorySubtract => {
+            process_memory(program_id, accounts, &instruction, |calc_data| {
+                let last_result = i64::from(calc_data.last_result);
+                let memory = i64::from(calc_data.memory)
+                    .checked_sub(last_result)
+                    .ok_or_else(|| {
+                        msg!("Invalid memory subtraction: memory is out of range");
+                        if last_result < 0 {
+                            CalculatorError::Overflow
+                        } else {
+                            CalculatorError::Underflow
+                        }
+                    })?;
+                calc_data.memory = memory.into();
+                msg!("Memory: {}", memory);
+                Ok(())
+            })
+        }
+        CalculatorInstruction::MemoryRecall => {
+            process_memory(program_id, accounts, &instruction, |calc_data| {
+                // Load the memory as the operand of the following accumulator
+                // instructions, like a calculator shows it on its display
+                calc_data.last_result = calc_data.memory;
+                calc_data.value = calc_data.memory;
+                msg!("Memory recalled: {}", i64::from(calc_data.memory));
+                Ok(())
+            })
+        }
+        CalculatorInstruction::MemoryClear => {
+            process_memory(program_id, accounts, &instruction, |calc_data| {
+                calc_data.memory = 0.into();
+                msg!("Memory cleared");
+                Ok(())
+            })
+        }
+        CalculatorInstruction::AccumulatorAdd { num } => {
+            process_accumulator(program_id, accounts, &instruction, |value| {
+                value.checked_add(num).ok_or_else(|| {
+                    msg!("Invalid accumulator addition: value is out of range");
+                    if num > 0 {
+                        CalculatorError::Overflow
+                    } else {
+                        CalculatorError::Underflow
+                    }
+                })
+            })
+        }
+        CalculatorInstruction::AccumulatorSubtract { num } => {
+            process_accumulator(program_id, accounts, &instruction, |value| {
+                value.checked_sub(num).ok_or_else(|| {
+                    msg!("Invalid accumulator subtraction: value is out of range");
+                    if num < 0 {
+                        CalculatorError::Overflow
+                    } else {
+                        CalculatorError::Underflow
+                    }
+                })
+            })
+        }
+        CalculatorInstruction::AccumulatorMultiply { num } => {
+            process_accumulator(program_id, accounts, &instruction, |value| {
+                value.checked_mul(num).ok_or_else(|| {
+                    msg!("Invalid accumulator multiplication: value is out of range");
+                    if (value < 0) == (num < 0) {
+                        CalculatorError::Overflow
+                    } else {
+                        CalculatorError::Underflow
+                    }
+                })
+            })
+        }
+        CalculatorInstruction::AccumulatorDivide { num } => {
+            process_accumulator(program_id, accounts, &instruction, |value| {
+                if num == 0 {
+                    msg!("Invalid accumulator division: num is zero");
+                    return Err(CalculatorError::DivideByZero);
+                }
+                // Only `i64::MIN / -1` is out of range
+                value.checked_div(num).ok_or_else(|| {
+                    msg!("Invalid accumulator division: value is out of range");
+                    CalculatorError::Overflow
+                })
+            })
+        }
+        CalculatorInstruction::AccumulatorDivideRounded { num, rounding } => {
+            process_accumulator(program_id, accounts, &instruction, |value| {
+                if num == 0 {
+                    msg!("Invalid accumulator division: num is zero");
+                    return Err(CalculatorError::DivideByZero);
+                }
+                div_rounded(value, num, rounding).ok_or_else(|| {
+                    msg!("Invalid accumulator division: value is out of range");
+                    CalculatorError::Overflow
+                })
+            })
+        }
+        CalculatorInstruction::AccumulatorSet { value } => {
+            process_accumulator(program_id, accounts, &instruction, |_| Ok(value))
+        }
+        CalculatorInstruction::Add { num1, num2 } => {
+            process_operation(program_id, accounts, &instruction, |calc_data| {
+                // Calculate the addition
+                let result = num1.checked_add(num2).ok_or_else(|| {
+                    msg!("Invalid addition operation: result overflows");
+                    CalculatorError::Overflow
+                })?;
+                calc_data.add_result = result.into();
+                msg!("Addition result: {}", result);
+                Ok(OperationResult::Unsigned(result))
+            })
+        }
+        CalculatorInstruction::Subtract { num1, num2 } => {
+            process_operation(program_id, accounts, &instruction, |calc_data| {
+                // Calculate the subtraction
+                let result = num1.checked_sub(num2).ok_or_else(|| {
+                    msg!("Invalid subtraction operation: num1 is less than num2");
+                    CalculatorError::Underflow
+                })?;
+                calc_data.sub_result = result.into();
+                msg!("Subtraction result: {}", result);
+                Ok(OperationResult::Unsigned(result))
+            })
+        }
+        CalculatorInstruction::Multiply { num1, num2 } => {
+            process_operation(program_id, accounts, &instruction, |calc_data| {
+                // Calculate the multiplication
+                let result = num1.checked_mul(num2).ok_or_else(|| {
+                    msg!("Invalid multiplication operation: result overflows");
+                    CalculatorError::Overflow
+                })?;
+                calc_data.mul_result = result.into();
+                msg!("Multiplication result: {}", result);
+                Ok(OperationResult::Unsigned(result))
+            })
+        }
+        CalculatorInstruction::Divide { num1, num2 } => {
+            process_operation(program_id, accounts, &instruction, |calc_data| {
+                // Calculate the integer division
+                let result = num1.checked_div(num2).ok_or_else(|| {
+                    msg!("Invalid division operation: num2 is zero");
+                    CalculatorError::DivideByZero
+                })?;
+                calc_data.div_result = result.into();
+                msg!("Division result: {}", result);
+                Ok(OperationResult::Unsigned(result))
+            })
+        }
+        CalculatorInstruction::Remainder { num1, num2 } => {
+            process_operation(program_id, accounts, &instruction, |calc_data| {
+                // Calculate the remainder
+                let result = num1.checked_rem(num2).ok_or_else(|| {
+                    msg!("Invalid remainder operation: num2 is zero");
+                    CalculatorError::DivideByZero
+                })?;
+                calc_data.rem_result = result.into();
+                msg!("Remainder result: {}", result);
+                Ok(OperationResult::Unsigned(result))
+            })
+        }
+        CalculatorInstruction::SignedAdd { num1, num2 } => {
+            process_operation(program_id, accounts, &instruction, |calc_data| {
+                // Calculate the signed addition
+                let result = num1.checked_add(num2).ok_or_else(|| {
+                    msg!("Invalid signed addition operation: result is out of range");
+                    if num2 > 0 {
+                        CalculatorError::Overflow
+                    } else {
+                        CalculatorError::Underflow
+                    }
+                })?;
+                calc_data.signed_add_result = result.into();
+                msg!("Signed addition result: {}", result);
+                Ok(OperationResult::Signed(result))
+            })
+        }
+        CalculatorInstruction::SignedSubtract { num1, num2 } => {
+            process_operation(program_id, accounts, &instruction, |calc_data| {
+                // Calculate the signed subtraction
+                let result = num1.checked_sub(num2).ok_or_else(|| {
+                    msg!("Invalid signed subtraction operation: result is out of range");
+                    if num2 < 0 {
+                        CalculatorError::Overflow
+                    } else {
+                        CalculatorError::Underflow
+                    }
+                })?;
+                calc_data.signed_sub_result = result.into();
+                msg!("Signed subtraction result: {}", result);
+                Ok(OperationResult::Signed(result))
+            })
+        }
+        CalculatorInstruction::Arithmetic {
+            operation,
+            operands,
+        } => process_operation(
+            program_id,
+            accounts,
+            &instruction,
+            |calc_data| match operands {
+                Operands::U32 { num1, num2 } => {
+                    let result = checked_operation(operation, num1, num2)?;
+                    let field = match operation {
+                        Operation::Add => &mut calc_data.add_result,
+                        Operation::Subtract => &mut calc_data.sub_result,
+                        Operation::Multiply => &mut calc_data.mul_result,
+                        Operation::Divide => &mut calc_data.div_result,
+                        Operation::Remainder => &mut calc_data.rem_result,
+                    };
+                    *field = result.into();
+                    Ok(OperationResult::Unsigned(result))
+                }
+                Operands::U64 { num1, num2 } => {
+                    let result = checked_operation(operation, num1, num2)?;
+                    calc_data.u64_result = result.into();
+                    Ok(OperationResult::U64(result))
+                }
+                Operands::U128 { num1, num2 } => {
+                    let result = checked_operation(operation, num1, num2)?;
+                    calc_data.u128_result = result.into();
+                    Ok(OperationResult::U128(result))
+                }
+            },
+        ),
+        CalculatorInstruction::DivideRounded { operands, rounding } => process_operation(
+            program_id,
+            accounts,
+            &instruction,
+            |calc_data| match operands {
+                Operands::U32 { num1, num2 } => {
+                    let result = rounded_division(num1, num2, rounding)?;
+                    calc_data.div_result = result.into();
+                    Ok(OperationResult::Unsigned(result))
+                }
+                Operands::U64 { num1, num2 } => {
+                    let result = rounded_division(num1, num2, rounding)?;
+                    calc_data.u64_result = result.into();
+                    Ok(OperationResult::U64(result))
+                }
+                Operands::U128 { num1, num2 } => {
+                    let result = rounded_division(num1, num2, rounding)?;
+                    calc_data.u128_result = result.into();
+                    Ok(OperationResult::U128(result))
+                }
+            },
+        ),
+        CalculatorInstruction::MulDiv { operands, rounding } => process_operation(
+            program_id,
+            accounts,
+            &instruction,
+            |calc_data| match operands {
+                MulDivOperands::U64 {
+                    num1,
+                    num2,
+                    denominator,
+                } => {
+                    let result = checked_mul_div(num1, num2, denominator, rounding)?;
+                    calc_data.u64_result = result.into();
+                    Ok(OperationResult::U64(result))
+                }
+                MulDivOperands::U128 {
+                    num1,
+                    num2,
+                    denominator,
+                } => {
+                    let result = checked_mul_div(num1, num2, denominator, rounding)?;
+                    calc_data.u128_result = result.into();
+                    Ok(OperationResult::U128(result))
+                }
+            },
+        ),
+        CalculatorInstruction::Pow { base, exponent } => {
+            process_operation(program_id, accounts, &instruction, |calc_data| {
+                let result = match base {
+                    Operand::U64(base) => checked_pow(base, exponent).map(Operand::U64),
+                    Operand::U128(base) => checked_pow(base, exponent).map(Operand::U128),
+                }
+                .ok_or_else(|| {
+                    msg!("Invalid pow operation: result overflows");
+                    CalculatorError::Overflow
+                })?;
+                Ok(store_wide_result(calc_data, "Pow", result))
+            })
+        }
+        CalculatorInstruction::Isqrt { value } => {
+            process_operation(program_id, accounts, &instruction, |calc_data| {
+                let result = match value {
+                    Operand::U64(value) => Operand::U64(isqrt(value)),
+                    Operand::U128(value) => Operand::U128(isqrt(value)),
+                };
+                Ok(store_wide_result(calc_data, "Isqrt", result))
+            })
+        }
+        CalculatorInstruction::NthRoot { value, degree } => {
+            process_operation(program_id, accounts, &instruction, |calc_data| {
+                let result = match value {
+                    Operand::U64(value) => nth_root(value, degree).map(Operand::U64),
+                    Operand::U128(value) => nth_root(value, degree).map(Operand::U128),
+                }
+                .ok_or_else(|| {
+                    msg!("Invalid root operation: degree is zero");
+                    CalculatorError::InvalidDegree
+                })?;
+                Ok(store_wide_result(calc_data, "NthRoot", result))
+            })
+        }
+        CalculatorInstruction::DecimalArithmetic {
+            operation,
+            num1,
+            num2,
+            scale,
+            rounding,
+        } => process_operation(program_id, accounts, &instruction, |calc_data| {
+            let result = num1.checked_operation(operation, num2, scale, rounding)?;
+            calc_data.decimal_result = result.into();
+            Ok(OperationResult::Decimal(result))
+        }),
+    }
+}
+
+// Apply `operation` to unsigned operands of any width
+fn checked_operation<T>(operation: Operation, num1: T, num2: T) -> Result<T, CalculatorError>
+where
+    T: PrimInt + CheckedRem + Display,
+{
+    let result = match operation {
+        Operation::Add => num1.checked_add(&num2).ok_or_else(|| {
+            msg!("Invalid addition operation: result overflows");
+            CalculatorError::Overflow
+        }),
+        Operation::Subtract => num1.checked_sub(&num2).ok_or_else(|| {
+            msg!("Invalid subtraction operation: num1 is less than num2");
+            CalculatorError::Underflow
+        }),
+        Operation::Multiply => num1.checked_mul(&num2).ok_or_else(|| {
+            msg!("Invalid multiplication operation: result overflows");
+            CalculatorError::Overflow
+        }),
+        Operation::Divide => num1.checked_div(&num2).ok_or_else(|| {
+            msg!("Invalid division operation: num2 is zero");
+            CalculatorError::DivideByZero
+        }),
+        Operation::Remainder => num1.checked_rem(&num2).ok_or_else(|| {
+            msg!("Invalid remainder operation: num2 is zero");
+            CalculatorError::DivideByZero
+        }),
+    }?;
+    msg!("{:?} result: {}", operation, result);
+    Ok(result)
+}
+
+// Divide unsigned operands of any width with the requested rounding
+fn rounded_division<T>(num1: T, num2: T, rounding: Rounding) -> Result<T, CalculatorError>
+where
+    T: PrimInt + Display,
+{
+    // Unsigned quotients always fit, so only a zero divisor fails
+    let result = div_rounded(num1, num2, rounding).ok_or_else(|| {
+        msg!("Invalid division operation: num2 is zero");
+        CalculatorError::DivideByZero
+    })?;
+    msg!("Division result rounded {:?}: {}", rounding, result);
+    Ok(result)
+}
+
+// Compute `num1 * num2 / denominator` for unsigned operands of any width up to
+// `u128`, with a full-width intermediate product
+fn checked_mul_div<T>(
+    num1: T,
+    num2: T,
+    denominator: T,
+    rounding: Rounding,
+) -> Result<T, CalculatorError>
+where
+    T: Into<u128> + TryFrom<u128> + Display,
+{
+    let denominator = denominator.into();
+    if denominator == 0 {
+        msg!("Invalid mul-div operation: denominator is zero");
+        return Err(CalculatorError::DivideByZero);
+    }
+    let result = mul_div(num1.into(), num2.into(), denominator, rounding)
+        .and_then(|result| T::try_from(result).ok())
+        .ok_or_else(|| {
+            msg!("Invalid mul-div operation: result overflows");
+            CalculatorError::Overflow
+        })?;
+    msg!("MulDiv result rounded {:?}: {}", rounding, result);
+    Ok(result)
+}
+
+// Store a `u64` or `u128` result in the field of its width
+fn store_wide_result(
+    calc_data: &mut PodCalcResult,
+    operation: &str,
+    result: Operand,
+) -> OperationResult {
+    match result {
+        Operand::U64(result) => {
+            msg!("{} result: {}", operation, result);
+            calc_data.u64_result = result.into();
+            OperationResult::U64(result)
+        }
+        Operand::U128(result) => {
+            msg!("{} result: {}", operation, result);
+            calc_data.u128_result = result.into();
+            OperationResult::U128(result)
+        }
+    }
+}
+
+// Get the calculator account and check it is owned by the program
+fn next_calculator_account<'a, 'b>(
+    program_id: &Pubkey,
+    accounts_iter: &mut Iter<'a, AccountInfo<'b>>,
+) -> Result<&'a AccountInfo<'b>, ProgramError> {
+    let calc_account = next_account_info(accounts_iter)?;
+
+    // The calculator account must be owned by the program
+    if calc_account.owner != program_id {
+        msg!("Calculator account does not have the correct program id");
+        return Err(CalculatorError::IncorrectAccountOwner.into());
+    }
+
+    Ok(calc_account)
+}
+
+// Set up the calculator state header in a fresh account
+fn process_initialize(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    history_capacity: u32,
+) -> ProgramResult {
+    // Iterating accounts is safer than indexing
+    let accounts_iter = &mut accounts.iter();
+    let calc_account = next_calculator_account(program_id, accounts_iter)?;
+    let authority_info = next_account_info(accounts_iter)?;
+    // Otherwise anyone could claim an account created out-of-band before its
+    // owner initializes it
+    if !calc_account.is_signer {
+        msg!("Calculator account must sign initialization");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if !authority_info.is_signer {
+        msg!("Calculator authority must sign initialization");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let mut data = calc_account.data.borrow_mut();
+    let space = CalcResult::space(history_capacity);
+    if data.len() < space {
+        msg!(
+            "Calculator account is too small: {} < {}",
+            data.len(),
+            space
+        );
+        return Err(ProgramError::AccountDataTooSmall);
+    }
+    if CalcResult::is_initialized(&data) {
+        msg!("Calculator account is already initialized");
+        return Err(CalculatorError::AccountAlreadyInitialized.into());
+    }
+
+    let calc_data = CalcResult {
+        history_capacity,
+        ..CalcResult::new(*authority_info.key)
+    };
+    calc_data.pack(&mut data)?;
+    msg!("Calculator account initialized");
+
+    Ok(())
+}
+
+// Create the user's calculator account at its program-derived address and
+// initialize it with the user as authority
+fn process_create_calculator(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    label: &str,
+    history_capacity: u32,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let calc_account = next_account_info(accounts_iter)?;
+    let user_info = next_account_info(accounts_iter)?;
+    let system_program_info = next_account_info(accounts_iter)?;
+
+    if !user_info.is_signer {
+        msg!("User must sign calculator account creation");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if !system_program::check_id(system_program_info.key) {
+        msg!("Expected the system program");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if label.len() > MAX_SEED_LEN {
+        msg!("Calculator label exceeds {} bytes", MAX_SEED_LEN);
+        return Err(ProgramError::MaxSeedLengthExceeded);
+    }
+
+    let (calc_address, bump) = find_calculator_address(program_id, user_info.key, label);
+    if calc_address != *calc_account.key {
+        msg!("Calculator account does not match the derived address");
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if calc_account.owner == program_id {
+        msg!("Calculator account already exists");
+        return Err(CalculatorError::AccountAlreadyInitialized.into());
+    }
+
+    let signer_seeds: &[&[u8]] = &[
+        CALCULATOR_SEED,
+        user_info.key.as_ref(),
+        label.as_bytes(),
+        &[bump],
+    ];
+    let cpi_accounts = [
+        user_info.clone(),
+        calc_account.clone(),
+        system_program_info.clone(),
+    ];
+    let space = CalcResult::space(history_capacity);
+    let rent_lamports = Rent::get()?.minimum_balance(space);
+
+    if calc_account.lamports() == 0 {
+        invoke_signed(
+            &system_instruction::create_account(
+                user_info.key,
+                calc_account.key,
+                rent_lamports,
+                space as u64,
+                program_id,
+            ),
+            &cpi_accounts,
+            &[signer_seeds],
+        )?;
+    } else {
+        // Someone already sent lamports to the address, so `create_account`
+        // would fail; top up, allocate and assign it instead
+        let shortfall = rent_lamports.saturating_sub(calc_account.lamports());
+        if shortfall > 0 {
+            invoke_signed(
+                &system_instruction::transfer(user_info.key, calc_account.key, shortfall),
+                &cpi_accounts,
+                &[signer_seeds],
+            )?;
+        }
+        invoke_signed(
+            &system_instruction::allocate(calc_account.key, space as u64),
+            &cpi_accounts,
+            &[signer_seeds],
+        )?;
+        invoke_signed(
+            &system_instruction::assign(calc_account.key, program_id),
+            &cpi_accounts,
+            &[signer_seeds],
+        )?;
+    }
+
+    let calc_data = CalcResult {
+        history_capacity,
+        ..CalcResult::new(*user_info.key)
+    };
+    calc_data.pack(&mut calc_account.data.borrow_mut())?;
+    msg!("Calculator account {} created", calc_address);
+
+    Ok(())
+}
+
+// Retire the calculator account, sending its lamports to the destination
+fn process_close(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let calc_account = next_calculator_account(program_id, accounts_iter)?;
+    let authority_info = next_account_info(accounts_iter)?;
+    let destination_info = next_account_info(accounts_iter)?;
+
+    check_authority(
+        PodCalcResult::load(&calc_account.data.borrow())?.0,
+        authority_info,
+    )?;
+    if destination_info.key == calc_account.key {
+        msg!("Cannot close a calculator account into itself");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    // Move every lamport out so the runtime reclaims the account
+    let lamports = calc_account.lamports();
+    **destination_info.lamports.borrow_mut() = destination_info
+        .lamports()
+        .checked_add(lamports)
+        .ok_or(CalculatorError::Overflow)?;
+    **calc_account.lamports.borrow_mut() = 0;
+
+    // Wipe the state and hand the account back to the system program so no
+    // calculator instruction accepts it again, even within this transaction
+    calc_account.data.borrow_mut().fill(0);
+    calc_account.assign(&system_program::id());
+    msg!("Calculator account closed, {} lamports reclaimed", lamports);
+
+    Ok(())
+}
+
+// Reallocate the calculator account for a new history capacity, keeping the
+// newest entries and settling the rent difference with the payer
+fn process_resize(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    history_capacity: u32,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let calc_account = next_calculator_account(program_id, accounts_iter)?;
+    let authority_info = next_account_info(accounts_iter)?;
+    let payer_info = next_account_info(accounts_iter)?;
+    let system_program_info = next_account_info(accounts_iter)?;
+
+    let data = calc_account.data.borrow();
+    let (calc_data, history_data) = PodCalcResult::load(&data)?;
+    check_authority(calc_data, authority_info)?;
+    if !payer_info.is_signer {
+        msg!("Payer must sign the resize");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if !system_program::check_id(system_program_info.key) {
+        msg!("Expected the system program");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if payer_info.key == calc_account.key {
+        msg!("Calculator account cannot pay for its own resize");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let history = calc_data.history(history_data)?;
+    drop(data);
+    let space = CalcResult::space(history_capacity);
+    calc_account.realloc(space, true)?;
+
+    // Lay the kept entries out again from the start of the resized buffer
+    let mut data = calc_account.data.borrow_mut();
+    let (calc_data, history_data) = PodCalcResult::load_mut(&mut data)?;
+    calc_data.history_capacity = history_capacity.into();
+    calc_data.history_len = 0.into();
+    calc_data.history_next = 0.into();
+    let kept = history.len().saturating_sub(history_capacity as usize);
+    for entry in &history[kept..] {
+        calc_data.push_history(history_data, entry)?;
+    }
+    drop(data);
+
+    let rent_lamports = top_up_rent(calc_account, payer_info, system_program_info)?;
+    let surplus = calc_account.lamports().saturating_sub(rent_lamports);
+    if surplus > 0 {
+        // Refund the rent the smaller account no longer needs
+        **payer_info.lamports.borrow_mut() = payer_info
+            .lamports()
+            .checked_add(surplus)
+            .ok_or(CalculatorError::Overflow)?;
+        **calc_account.lamports.borrow_mut() = rent_lamports;
+    }
+    msg!(
+        "Calculator account resized to {} bytes, {} history entries",
+        space,
+        history_capacity
+    );
+
+    Ok(())
+}
+
+// Rewrite a calculator account of an older layout version in the current one,
+// growing it as needed
+fn process_migrate(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let calc_account = next_calculator_account(program_id, accounts_iter)?;
+    let authority_info = next_account_info(accounts_iter)?;
+    let payer_info = next_account_info(accounts_iter)?;
+    let system_program_info = next_account_info(accounts_iter)?;
+
+    if !payer_info.is_signer {
+        msg!("Payer must sign the migration");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if !system_program::check_id(system_program_info.key) {
+        msg!("Expected the system program");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let data = calc_account.data.borrow();
+    let versioned = VersionedCalcResult::unpack(&data)?;
+    match &versioned {
+        VersionedCalcResult::Current(_) => {
+            msg!("Calculator account already uses the current layout");
+            return Ok(());
+        }
+        // Version 1 accounts have no authority yet; they were created from
+        // keypairs, so only the account's own signature proves ownership
+        VersionedCalcResult::V1(_) => {
+            if !calc_account.is_signer {
+                msg!("Version 1 calculator accounts must sign their own migration");
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+            if !authority_info.is_signer {
+                msg!("Calculator authority signature missing");
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+        }
+        VersionedCalcResult::Outdated(calc_data) => {
+            check_authority(&PodCalcResult::from(calc_data), authority_info)?
+        }
+    }
+    let history = versioned.history(&data)?;
+    drop(data);
+    let mut calc_data = CalcResult {
+        history_len: 0,
+        history_next: 0,
+        ..versioned.into_current(*authority_info.key)
+    };
+
+    // An instruction can grow an account by at most
+    // `MAX_PERMITTED_DATA_INCREASE`, so keep the history capacity that fits
+    let max_space = calc_account.data_len() + MAX_PERMITTED_DATA_INCREASE;
+    let max_capacity = ((max_space - CalcResult::LEN) / HistoryEntry::LEN) as u32;
+    if calc_data.history_capacity > max_capacity {
+        msg!(
+            "History capacity reduced from {} to {} entries to fit the migration",
+            calc_data.history_capacity,
+            max_capacity
+        );
+        calc_data.history_capacity = max_capacity;
+    }
+    let space = CalcResult::space(calc_data.history_capacity).max(calc_account.data_len());
+    calc_account.realloc(space, true)?;
+
+    // Lay the converted entries out again after the grown state, dropping the
+    // oldest ones beyond the capacity
+    let mut data = calc_account.data.borrow_mut();
+    calc_data.pack(&mut data)?;
+    let (calc_data, history_data) = PodCalcResult::load_mut(&mut data)?;
+    history_data.fill(0);
+    let kept = history
+        .len()
+        .saturating_sub(u32::from(calc_data.history_capacity) as usize);
+    for entry in &history[kept..] {
+        calc_data.push_history(history_data, entry)?;
+    }
+    drop(data);
+
+    top_up_rent(calc_account, payer_info, system_program_info)?;
+    msg!(
+        "Calculator account migrated to version {}",
+        CalcResult::VERSION
+    );
+
+    Ok(())
+}
+
+// Transfer whatever the calculator account lacks to be rent exempt at its
+// current size from the payer, returning the rent-exempt balance
+fn top_up_rent<'a>(
+    calc_account: &AccountInfo<'a>,
+    payer_info: &AccountInfo<'a>,
+    system_program_info: &AccountInfo<'a>,
+) -> Result<u64, ProgramError> {
+    let rent_lamports = Rent::get()?.minimum_balance(calc_account.data_len());
+    let shortfall = rent_lamports.saturating_sub(calc_account.lamports());
+    if shortfall > 0 {
+        invoke(
+            &system_instruction::transfer(payer_info.key, calc_account.key, shortfall),
+            &[
+                payer_info.clone(),
+                calc_account.clone(),
+                system_program_info.clone(),
+            ],
+        )?;
+    }
+    Ok(rent_lamports)
+}
+
+// Run an arithmetic operation against the calculator state, or against
+// scratch state when no accounts are given, and publish its result through
+// the return data
+fn process_operation<F>(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction: &CalculatorInstruction,
+    operation: F,
+) -> ProgramResult
+where
+    F: FnOnce(&mut PodCalcResult) -> Result<OperationResult, ProgramError>,
+{
+    // Without any accounts only compute the result, leaving no state behind
+    if accounts.is_empty() {
+        msg!("Compute-only mode, result is not stored");
+        let result = operation(&mut PodCalcResult::zeroed())?;
+        set_return_data(&result.try_to_vec()?);
+        return Ok(());
+    }
+
+    record_operation(program_id, accounts, instruction, |calc_data| {
+        let result = operation(calc_data)?;
+        match result {
+            OperationResult::Unsigned(value) => calc_data.last_result = i64::from(value).into(),
+            OperationResult::Signed(value) => calc_data.last_result = value.into(),
+            // Wider and decimal results do not fit the memory register
+            OperationResult::U64(_) | OperationResult::U128(_) | OperationResult::Decimal(_) => {}
+        }
+        Ok(result)
+    })
+}
+
+// Run a memory register instruction against the calculator state and publish
+// the resulting memory value through the return data
+fn process_memory<F>(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction: &CalculatorInstruction,
+    operation: F,
+) -> ProgramResult
+where
+    F: FnOnce(&mut PodCalcResult) -> ProgramResult,
+{
+    record_operation(program_id, accounts, instruction, |calc_data| {
+        operation(calc_data)?;
+        Ok(OperationResult::Signed(calc_data.memory.into()))
+    })
+}
+
+// Replace the accumulator value with `operation` applied to it, making it the
+// last result, and publish it through the return data
+fn process_accumulator<F>(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction: &CalculatorInstruction,
+    operation: F,
+) -> ProgramResult
+where
+    F: FnOnce(i64) -> Result<i64, CalculatorError>,
+{
+    record_operation(program_id, accounts, instruction, |calc_data| {
+        let value = operation(calc_data.value.into())?;
+        calc_data.value = value.into();
+        calc_data.last_result = value.into();
+        msg!("Accumulator value: {}", value);
+        Ok(OperationResult::Signed(value))
+    })
+}
+
+// Apply `operation` to the calculator state with `update_state`, publish its
+// result through the return data and record it in the account's history
+fn record_operation<F>(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction: &CalculatorInstruction,
+    operation: F,
+) -> ProgramResult
+where
+    F: FnOnce(&mut PodCalcResult) -> Result<OperationResult, ProgramError>,
+{
+    update_state(program_id, accounts, |calc_data, history| {
+        // The authority has been checked to be the signer
+        let signer = calc_data.authority;
+        let result = operation(calc_data)?;
+        set_return_data(&result.try_to_vec()?);
+
+        if u32::from(calc_data.history_capacity) > 0 {
+            let entry = HistoryEntry {
+                slot: Clock::get()?.slot,
+                signer,
+                result,
+                instruction: instruction.clone(),
+            };
+            calc_data.push_history(history, &entry)?;
+        }
+        Ok(())
+    })
+}
+
+// Check the authority's signature and apply `update` to the calculator state
+// and its history buffer in place
+fn update_state<F>(program_id: &Pubkey, accounts: &[AccountInfo], update: F) -> ProgramResult
+where
+    F: FnOnce(&mut PodCalcResult, &mut [u8]) -> ProgramResult,
+{
+    let accounts_iter = &mut accounts.iter();
+    let calc_account = next_calculator_account(program_id, accounts_iter)?;
+    let authority_info = next_account_info(accounts_iter)?;
+
+    let mut data = calc_account.data.borrow_mut();
+    #[cfg(not(feature = "borsh-state-baseline"))]
+    {
+        let (calc_data, history) = PodCalcResult::load_mut(&mut data)?;
+        check_authority(calc_data, authority_info)?;
+
+        update(calc_data, history)
+    }
+    // Decode and re-encode the whole state, the work the zero-copy view saves
+    #[cfg(feature = "borsh-state-baseline")]
+    {
+        let mut calc_data = PodCalcResult::from(&CalcResult::unpack(&data)?);
+        check_authority(&calc_data, authority_info)?;
+
+        update(&mut calc_data, &mut data[CalcResult::LEN..])?;
+        CalcResult::from(&calc_data).pack(&mut data)
+    }
+}
+
+// Only the recorded authority may modify the account
+fn check_authority(calc_data: &PodCalcResult, authority_info: &AccountInfo) -> ProgramResult {
+    if calc_data.authority != *authority_info.key {
+        msg!("Calculator authority does not match");
+        return Err(CalculatorError::InvalidAuthority.into());
+    }
+    if !authority_info.is_signer {
+        msg!("Calculator authority signature missing");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    Ok(())
+}
+
+// Tests for the calculator program
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        decimal::Decimal,
+        state::{decode_history, CalcResultV1},
+        test_utils::{TestAccount, TestSyscallStubs, CPI_PROGRAM_ID, TEST_SLOT},
+    };
+    use solana_program::program_stubs;
+
+    // Send `Initialize` for the calculator account in `accounts[0]`, signed by
+    // it as it is when created out-of-band
+    fn try_initialize(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        history_capacity: u32,
+    ) -> ProgramResult {
+        let mut calc_account = accounts[0].clone();
+        calc_account.is_signer = true;
+        let accounts = [&[calc_account], &accounts[1..]].concat();
+        let instruction_data = CalculatorInstruction::Initialize { history_capacity }.pack();
+        handle_instruction(program_id, &accounts, &instruction_data)
+    }
+
+    fn initialize(program_id: &Pubkey, accounts: &[AccountInfo]) {
+        try_initialize(program_id, accounts, 0).unwrap();
+    }
+
+    #[test]
+    fn test_calculator_operations() {
+        let program_id = Pubkey::default();
+        let mut calc = TestAccount::new(CalcResult::LEN, &program_id);
+        let mut authority = TestAccount::new(0, &Pubkey::default());
+
+        let num1: u32 = 100;
+        let num2: u32 = 30;
+        let add_instruction_data = CalculatorInstruction::Add { num1, num2 }.pack();
+
+        let accounts = vec![calc.info(false, true), authority.info(true, false)];
+        initialize(&program_id, &accounts);
+
+        assert_eq!(
+            CalcResult::unpack(&accounts[0].data.borrow())
+                .unwrap()
+                .add_result,
+            0
+        );
+
+        handle_instruction(&program_id, &accounts, &add_instruction_data).unwrap();
+
+        assert_eq!(
+            CalcResult::unpack(&accounts[0].data.borrow())
+                .unwrap()
+                .add_result,
+            num1 + num2
+        );
+
+        // Test the subtraction operation
+        let sub_instruction_data = CalculatorInstruction::Subtract { num1, num2 }.pack();
+
+        handle_instruction(&program_id, &accounts, &sub_instruction_data).unwrap();
+
+        assert_eq!(
+            CalcResult::unpack(&accounts[0].data.borrow())
+                .unwrap()
+                .sub_result,
+            num1 - num2
+        );
+    }
+
+    #[test]
+    fn test_legacy_instruction_data() {
+        let program_id = Pubkey::default();
+
+        let num1: u32 = 100;
+        let num2: u32 = 30;
+        let payloads = [
+            (
+                [num1.to_le_bytes(), num2.to_le_bytes(), 0u32.to_le_bytes()].concat(),
+                CalculatorInstruction::Add { num1, num2 }.pack(),
+            ),
+            (
+                [num1.to_le_bytes(), num2.to_le_bytes(), 1u32.to_le_bytes()].concat(),
+                CalculatorInstruction::Subtract { num1, num2 }.pack(),
+            ),
+        ];
+
+        for (legacy_data, borsh_data) in payloads {
+            let mut results = Vec::new();
+            for instruction_data in [legacy_data, borsh_data] {
+                let mut calc = TestAccount::new(CalcResult::LEN, &program_id);
+                let mut authority = TestAccount::new(0, &Pubkey::default());
+                // Keep the authority identical so only the operation differs
+                authority.key = Pubkey::default();
+                {
+                    let accounts = [calc.info(false, true), authority.info(true, false)];
+                    initialize(&program_id, &accounts);
+                    handle_instruction(&program_id, &accounts, &instruction_data).unwrap();
+                }
+                results.push(calc.data().to_vec());
+            }
+
+            // Old payloads must leave the account in exactly the same state
+            assert_eq!(results[0], results[1]);
+        }
+    }
+
+    #[test]
+    fn test_multiplication_division_remainder() {
+        let program_id = Pubkey::default();
+        let mut calc = TestAccount::new(CalcResult::LEN, &program_id);
+        let mut authority = TestAccount::new(0, &Pubkey::default());
+        let accounts = vec![calc.info(false, true), authority.info(true, false)];
+        initialize(&program_id, &accounts);
+
+        let num1: u32 = 100;
+        let num2: u32 = 30;
+        for instruction in [
+            CalculatorInstruction::Multiply { num1, num2 },
+            CalculatorInstruction::Divide { num1, num2 },
+            CalculatorInstruction::Remainder { num1, num2 },
+        ] {
+            handle_instruction(&program_id, &accounts, &instruction.pack()).unwrap();
+        }
+
+        let result = CalcResult::unpack(&accounts[0].data.borrow()).unwrap();
+        assert_eq!(result.mul_result, 3000);
+        assert_eq!(result.div_result, 3);
+        assert_eq!(result.rem_result, 10);
+
+        // Division by zero is reported rather than panicking
+        for instruction in [
+            CalculatorInstruction::Divide { num1, num2: 0 },
+            CalculatorInstruction::Remainder { num1, num2: 0 },
+        ] {
+            assert_eq!(
+                handle_instruction(&program_id, &accounts, &instruction.pack()),
+                Err(CalculatorError::DivideByZero.into())
+            );
+        }
+    }
+
+    #[test]
+    fn test_boundary_values() {
+        let program_id = Pubkey::default();
+        let mut calc = TestAccount::new(CalcResult::LEN, &program_id);
+        let mut authority = TestAccount::new(0, &Pubkey::default());
+        let accounts = vec![calc.info(false, true), authority.info(true, false)];
+        initialize(&program_id, &accounts);
+
+        let cases = [
+            (
+                CalculatorInstruction::Add {
+                    num1: u32::MAX,
+                    num2: 0,
+                },
+                Ok(()),
+            ),
+            (
+                CalculatorInstruction::Add {
+                    num1: u32::MAX,
+                    num2: 1,
+                },
+                Err(CalculatorError::Overflow.into()),
+            ),
+            (CalculatorInstruction::Subtract { num1: 0, num2: 0 }, Ok(())),
+            (
+                CalculatorInstruction::Subtract {
+                    num1: u32::MAX,
+                    num2: u32::MAX,
+                },
+                Ok(()),
+            ),
+            (
+                CalculatorInstruction::Subtract { num1: 0, num2: 1 },
+                Err(CalculatorError::Underflow.into()),
+            ),
+            (
+                CalculatorInstruction::Multiply {
+                    num1: u32::MAX,
+                    num2: 1,
+                },
+                Ok(()),
+            ),
+            (
+                CalculatorInstruction::Multiply {
+                    num1: 65535,
+                    num2: 65537,
+                },
+                Ok(()),
+            ),
+            (
+                CalculatorInstruction::Multiply {
+                    num1: 65536,
+                    num2: 65536,
+                },
+                Err(CalculatorError::Overflow.into()),
+            ),
+            (
+                CalculatorInstruction::Divide {
+                    num1: u32::MAX,
+                    num2: 1,
+                },
+                Ok(()),
+            ),
+            (
+                CalculatorInstruction::Divide {
+                    num1: 0,
+                    num2: u32::MAX,
+                },
+                Ok(()),
+            ),
+            (
+                CalculatorInstruction::Remainder {
+                    num1: u32::MAX,
+                    num2: u32::MAX,
+                },
+                Ok(()),
+            ),
+            (
+                CalculatorInstruction::Remainder {
+                    num1: u32::MAX - 1,
+                    num2: u32::MAX,
+                },
+                Ok(()),
+            ),
+        ];
+
+        for (instruction, expected) in cases {
+            assert_eq!(
+                handle_instruction(&program_id, &accounts, &instruction.pack()),
+                expected,
+                "{:?}",
+                instruction
+            );
+        }
+
+        // A failed operation must not clobber the previously stored result
+        handle_instruction(
+            &program_id,
+            &accounts,
+            &CalculatorInstruction::Add {
+                num1: u32::MAX - 1,
+                num2: 1,
+            }
+            .pack(),
+        )
+        .unwrap();
+        let _ = handle_instruction(
+            &program_id,
+            &accounts,
+            &CalculatorInstruction::Add {
+                num1: u32::MAX,
+                num2: 1,
+            }
+            .pack(),
+        );
+        let result = CalcResult::unpack(&accounts[0].data.borrow()).unwrap();
+        assert_eq!(result.add_result, u32::MAX);
+        assert_eq!(result.rem_result, u32::MAX - 1);
+    }
+
+    #[test]
+    fn test_signed_operations() {
+        let program_id = Pubkey::default();
+        let mut calc = TestAccount::new(CalcResult::LEN, &program_id);
+        let mut authority = TestAccount::new(0, &Pubkey::default());
+        let accounts = vec![calc.info(false, true), authority.info(true, false)];
+        initialize(&program_id, &accounts);
+
+        // Negative results are stored rather than rejected
+        for instruction in [
+            CalculatorInstruction::SignedAdd { num1: -7, num2: 4 },
+            CalculatorInstruction::SignedSubtract { num1: 3, num2: 5 },
+        ] {
+            handle_instruction(&program_id, &accounts, &instruction.pack()).unwrap();
+        }
+        let result = CalcResult::unpack(&accounts[0].data.borrow()).unwrap();
+        assert_eq!(result.signed_add_result, -3);
+        assert_eq!(result.signed_sub_result, -2);
+
+        // The unsigned subtraction still rejects negative results
+        assert_eq!(
+            handle_instruction(
+                &program_id,
+                &accounts,
+                &CalculatorInstruction::Subtract { num1: 3, num2: 5 }.pack()
+            ),
+            Err(CalculatorError::Underflow.into())
+        );
+
+        let cases = [
+            (
+                CalculatorInstruction::SignedAdd {
+                    num1: i64::MAX,
+                    num2: 0,
+                },
+                Ok(()),
+            ),
+            (
+                CalculatorInstruction::SignedAdd {
+                    num1: i64::MIN,
+                    num2: 0,
+                },
+                Ok(()),
+            ),
+            (
+                CalculatorInstruction::SignedAdd {
+                    num1: i64::MAX,
+                    num2: 1,
+                },
+                Err(CalculatorError::Overflow.into()),
+            ),
+            (
+                CalculatorInstruction::SignedAdd {
+                    num1: i64::MIN,
+                    num2: -1,
+                },
+                Err(CalculatorError::Underflow.into()),
+            ),
+            (
+                CalculatorInstruction::SignedSubtract {
+                    num1: -1,
+                    num2: i64::MAX,
+                },
+                Ok(()),
+            ),
+            (
+                CalculatorInstruction::SignedSubtract {
+                    num1: 0,
+                    num2: i64::MIN,
+                },
+                Err(CalculatorError::Overflow.into()),
+            ),
+            (
+                CalculatorInstruction::SignedSubtract {
+                    num1: -2,
+                    num2: i64::MAX,
+                },
+                Err(CalculatorError::Underflow.into()),
+            ),
+        ];
+        for (instruction, expected) in cases {
+            assert_eq!(
+                handle_instruction(&program_id, &accounts, &instruction.pack()),
+                expected,
+                "{:?}",
+                instruction
+            );
+        }
+    }
+
+    #[test]
+    fn test_initialize() {
+        let program_id = Pubkey::default();
+        let mut calc = TestAccount::new(CalcResult::LEN, &program_id);
+        let mut authority = TestAccount::new(0, &Pubkey::default());
+        let authority_key = authority.key;
+        let accounts = vec![calc.info(false, true), authority.info(true, false)];
+        let add_instruction_data = CalculatorInstruction::Add { num1: 1, num2: 2 }.pack();
+
+        // Operations on a zeroed account are rejected
+        assert_eq!(
+            handle_instruction(&program_id, &accounts, &add_instruction_data),
+            Err(CalculatorError::AccountNotInitialized.into())
+        );
+
+        initialize(&program_id, &accounts);
+        assert_eq!(
+            CalcResult::unpack(&accounts[0].data.borrow()).unwrap(),
+            CalcResult::new(authority_key)
+        );
+        handle_instruction(&program_id, &accounts, &add_instruction_data).unwrap();
+
+        // Initializing twice would wipe the stored results
+        assert_eq!(
+            try_initialize(&program_id, &accounts, 0),
+            Err(CalculatorError::AccountAlreadyInitialized.into())
+        );
+        assert_eq!(
+            CalcResult::unpack(&accounts[0].data.borrow())
+                .unwrap()
+                .add_result,
+            3
+        );
+
+        // Accounts too small for the state cannot be initialized
+        let mut small = TestAccount::new(CalcResult::LEN - 1, &program_id);
+        let mut authority = TestAccount::new(0, &Pubkey::default());
+        assert_eq!(
+            try_initialize(
+                &program_id,
+                &[small.info(false, true), authority.info(true, false)],
+                0
+            ),
+            Err(ProgramError::AccountDataTooSmall)
+        );
+
+        // The authority must sign initialization
+        let mut calc = TestAccount::new(CalcResult::LEN, &program_id);
+        assert_eq!(
+            try_initialize(
+                &program_id,
+                &[calc.info(false, true), authority.info(false, false)],
+                0
+            ),
+            Err(ProgramError::MissingRequiredSignature)
+        );
+
+        // So must the calculator account, or anyone could make themselves the
+        // authority of an account created out-of-band
+        let mut intruder = TestAccount::new(0, &Pubkey::default());
+        let accounts = [calc.info(false, true), intruder.info(true, false)];
+        assert_eq!(
+            handle_instruction(
+                &program_id,
+                &accounts,
+                &CalculatorInstruction::Initialize {
+                    history_capacity: 0
+                }
+                .pack()
+            ),
+            Err(ProgramError::MissingRequiredSignature)
+        );
+        assert!(!CalcResult::is_initialized(&accounts[0].data.borrow()));
+    }
+
+    #[test]
+    fn test_authority() {
+        let program_id = Pubkey::default();
+        let mut calc = TestAccount::new(CalcResult::LEN, &program_id);
+        let mut authority = TestAccount::new(0, &Pubkey::default());
+        let mut new_authority = TestAccount::new(0, &Pubkey::default());
+        let new_authority_key = new_authority.key;
+
+        let calc_account = calc.info(false, true);
+        let authority_account = authority.info(true, false);
+        let new_authority_account = new_authority.info(true, false);
+        initialize(
+            &program_id,
+            &[calc_account.clone(), authority_account.clone()],
+        );
+
+        let add_instruction_data = CalculatorInstruction::Add { num1: 1, num2: 2 }.pack();
+
+        // Anyone other than the authority is rejected
+        assert_eq!(
+            handle_instruction(
+                &program_id,
+                &[calc_account.clone(), new_authority_account.clone()],
+                &add_instruction_data
+            ),
+            Err(CalculatorError::InvalidAuthority.into())
+        );
+
+        // The authority must sign
+        let mut unsigned_authority = authority_account.clone();
+        unsigned_authority.is_signer = false;
+        assert_eq!(
+            handle_instruction(
+                &program_id,
+                &[calc_account.clone(), unsigned_authority],
+                &add_instruction_data
+            ),
+            Err(ProgramError::MissingRequiredSignature)
+        );
+
+        // Hand the account over; the old authority loses access
+        handle_instruction(
+            &program_id,
+            &[calc_account.clone(), authority_account.clone()],
+            &CalculatorInstruction::SetAuthority {
+                new_authority: new_authority_key,
+            }
+            .pack(),
+        )
+        .unwrap();
+        assert_eq!(
+            CalcResult::unpack(&calc_account.data.borrow())
+                .unwrap()
+                .authority,
+            new_authority_key
+        );
+        assert_eq!(
+            handle_instruction(
+                &program_id,
+                &[calc_account.clone(), authority_account],
+                &add_instruction_data
+            ),
+            Err(CalculatorError::InvalidAuthority.into())
+        );
+        handle_instruction(
+            &program_id,
+            &[calc_account, new_authority_account],
+            &add_instruction_data,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_create_calculator() {
+        program_stubs::set_syscall_stubs(Box::new(TestSyscallStubs));
+        let program_id = CPI_PROGRAM_ID;
+        let label = "savings".to_string();
+        let create_instruction_data = CalculatorInstruction::CreateCalculator {
+            history_capacity: 0,
+            label: label.clone(),
+        }
+        .pack();
+
+        let mut user = TestAccount::new(0, &system_program::id());
+        user.lamports = 1_000_000_000;
+        let mut system = TestAccount::new(0, &Pubkey::default());
+        system.key = system_program::id();
+        let (calc_address, _) = find_calculator_address(&program_id, &user.key, &label);
+
+        // The account must be the derived address for the user and label
+        let mut wrong = TestAccount::new(CalcResult::LEN, &system_program::id());
+        assert_eq!(
+            handle_instruction(
+                &program_id,
+                &[
+                    wrong.info(false, true),
+                    user.info(true, true),
+                    system.info(false, false)
+                ],
+                &create_instruction_data
+            ),
+            Err(ProgramError::InvalidSeeds)
+        );
+
+        let mut calc = TestAccount::new(CalcResult::LEN, &system_program::id());
+        calc.key = calc_address;
+        let user_account = user.info(true, true);
+        let accounts = [
+            calc.info(false, true),
+            user_account.clone(),
+            system.info(false, false),
+        ];
+
+        // The user must sign
+        let mut unsigned_user = user_account.clone();
+        unsigned_user.is_signer = false;
+        assert_eq!(
+            handle_instruction(
+                &program_id,
+                &[accounts[0].clone(), unsigned_user, accounts[2].clone()],
+                &create_instruction_data
+            ),
+            Err(ProgramError::MissingRequiredSignature)
+        );
+
+        handle_instruction(&program_id, &accounts, &create_instruction_data).unwrap();
+
+        let rent_lamports = Rent::default().minimum_balance(CalcResult::LEN);
+        assert_eq!(accounts[0].owner, &program_id);
+        assert_eq!(accounts[0].lamports(), rent_lamports);
+        assert_eq!(user_account.lamports(), 1_000_000_000 - rent_lamports);
+        assert_eq!(
+            CalcResult::unpack(&accounts[0].data.borrow()).unwrap(),
+            CalcResult::new(*user_account.key)
+        );
+
+        // The user is the authority of the new account
+        handle_instruction(
+            &program_id,
+            &accounts[..2],
+            &CalculatorInstruction::Add { num1: 1, num2: 2 }.pack(),
+        )
+        .unwrap();
+
+        // The account cannot be created twice
+        assert_eq!(
+            handle_instruction(&program_id, &accounts, &create_instruction_data),
+            Err(CalculatorError::AccountAlreadyInitialized.into())
+        );
+    }
+
+    #[test]
+    fn test_create_calculator_prefunded() {
+        program_stubs::set_syscall_stubs(Box::new(TestSyscallStubs));
+        let program_id = CPI_PROGRAM_ID;
+
+        let mut user = TestAccount::new(0, &system_program::id());
+        user.lamports = 1_000_000_000;
+        let mut system = TestAccount::new(0, &Pubkey::default());
+        system.key = system_program::id();
+
+        // Lamports sent to the address ahead of time must not block creation
+        let mut calc = TestAccount::new(CalcResult::LEN, &system_program::id());
+        calc.key = find_calculator_address(&program_id, &user.key, "").0;
+        calc.lamports = 1;
+
+        let accounts = [
+            calc.info(false, true),
+            user.info(true, true),
+            system.info(false, false),
+        ];
+        handle_instruction(
+            &program_id,
+            &accounts,
+            &CalculatorInstruction::CreateCalculator {
+                history_capacity: 0,
+                label: String::new(),
+            }
+            .pack(),
+        )
+        .unwrap();
+
+        assert_eq!(accounts[0].owner, &program_id);
+        assert_eq!(
+            accounts[0].lamports(),
+            Rent::default().minimum_balance(CalcResult::LEN)
+        );
+        assert!(CalcResult::unpack(&accounts[0].data.borrow()).is_ok());
+    }
+
+    #[test]
+    fn test_close() {
+        let program_id = Pubkey::new_unique();
+        let mut calc = TestAccount::new(CalcResult::LEN, &program_id);
+        calc.lamports = 1_000;
+        let mut authority = TestAccount::new(0, &Pubkey::default());
+        let mut destination = TestAccount::new(0, &Pubkey::default());
+        destination.lamports = 5;
+
+        let calc_account = calc.info(false, true);
+        let authority_account = authority.info(true, false);
+        let destination_account = destination.info(false, true);
+        initialize(
+            &program_id,
+            &[calc_account.clone(), authority_account.clone()],
+        );
+        let close_instruction_data = CalculatorInstruction::Close.pack();
+
+        // Only the authority may close the account
+        let mut unsigned_authority = authority_account.clone();
+        unsigned_authority.is_signer = false;
+        assert_eq!(
+            handle_instruction(
+                &program_id,
+                &[
+                    calc_account.clone(),
+                    unsigned_authority,
+                    destination_account.clone()
+                ],
+                &close_instruction_data
+            ),
+            Err(ProgramError::MissingRequiredSignature)
+        );
+        assert_eq!(
+            handle_instruction(
+                &program_id,
+                &[
+                    calc_account.clone(),
+                    authority_account.clone(),
+                    calc_account.clone()
+                ],
+                &close_instruction_data
+            ),
+            Err(ProgramError::InvalidArgument)
+        );
+
+        let accounts = [
+            calc_account.clone(),
+            authority_account.clone(),
+            destination_account.clone(),
+        ];
+        handle_instruction(&program_id, &accounts, &close_instruction_data).unwrap();
+
+        assert_eq!(calc_account.lamports(), 0);
+        assert_eq!(destination_account.lamports(), 1_005);
+        assert!(calc_account.data.borrow().iter().all(|byte| *byte == 0));
+        assert_eq!(calc_account.owner, &system_program::id());
+
+        // The closed account is refused by every instruction
+        for instruction in [
+            CalculatorInstruction::Add { num1: 1, num2: 2 },
+            CalculatorInstruction::Initialize {
+                history_capacity: 0,
+            },
+            CalculatorInstruction::Close,
+        ] {
+            assert_eq!(
+                handle_instruction(&program_id, &accounts, &instruction.pack()),
+                Err(CalculatorError::IncorrectAccountOwner.into())
+            );
+        }
+    }
+
+    #[test]
+    fn test_return_data() {
+        program_stubs::set_syscall_stubs(Box::new(TestSyscallStubs));
+        let program_id = CPI_PROGRAM_ID;
+        let mut calc = TestAccount::new(CalcResult::LEN, &program_id);
+        let mut authority = TestAccount::new(0, &Pubkey::default());
+        let accounts = vec![calc.info(false, true), authority.info(true, false)];
+        initialize(&program_id, &accounts);
+
+        let cases = [
+            (
+                CalculatorInstruction::Add {
+                    num1: 100,
+                    num2: 30,
+                },
+                OperationResult::Unsigned(130),
+            ),
+            (
+                CalculatorInstruction::Remainder {
+                    num1: 100,
+                    num2: 30,
+                },
+                OperationResult::Unsigned(10),
+            ),
+            (
+                CalculatorInstruction::SignedSubtract { num1: 3, num2: 5 },
+                OperationResult::Signed(-2),
+            ),
+        ];
+        for (instruction, expected) in cases {
+            handle_instruction(&program_id, &accounts, &instruction.pack()).unwrap();
+            assert_eq!(
+                OperationResult::from_return_data(&program_id),
+                Some(expected)
+            );
+        }
+
+        // Results published by other programs are ignored
+        assert_eq!(
+            OperationResult::from_return_data(&Pubkey::new_unique()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_resize() {
+        program_stubs::set_syscall_stubs(Box::new(TestSyscallStubs));
+        let program_id = CPI_PROGRAM_ID;
+        let rent = Rent::default();
+
+        let mut calc = TestAccount::new(CalcResult::space(1), &program_id);
+        calc.lamports = rent.minimum_balance(CalcResult::space(1));
+        let mut authority = TestAccount::new(0, &Pubkey::default());
+        let mut payer = TestAccount::new(0, &system_program::id());
+        payer.lamports = 1_000_000_000;
+        let mut system = TestAccount::new(0, &Pubkey::default());
+        system.key = system_program::id();
+
+        let calc_account = calc.info(false, true);
+        let authority_account = authority.info(true, false);
+        let payer_account = payer.info(true, true);
+        let accounts = [
+            calc_account.clone(),
+            authority_account.clone(),
+            payer_account.clone(),
+            system.info(false, false),
+        ];
+        try_initialize(&program_id, &accounts[..2], 1).unwrap();
+        let add = |num1: u32| {
+            let instruction = CalculatorInstruction::Add { num1, num2: 0 };
+            handle_instruction(&program_id, &accounts[..2], &instruction.pack()).unwrap();
+        };
+        let resize = |history_capacity| {
+            let instruction = CalculatorInstruction::Resize { history_capacity };
+            handle_instruction(&program_id, &accounts, &instruction.pack())
+        };
+        let history_results = || {
+            decode_history(&calc_account.data.borrow())
+                .unwrap()
+                .iter()
+                .map(|entry| entry.result)
+                .collect::<Vec<_>>()
+        };
+        add(1);
+
+        // Growing keeps the state and history and takes rent from the payer
+        resize(3).unwrap();
+        let grown_rent = rent.minimum_balance(CalcResult::space(3));
+        assert_eq!(calc_account.data_len(), CalcResult::space(3));
+        assert_eq!(calc_account.lamports(), grown_rent);
+        assert_eq!(
+            payer_account.lamports(),
+            1_000_000_000 - (grown_rent - rent.minimum_balance(CalcResult::space(1)))
+        );
+        assert_eq!(
+            CalcResult::unpack(&calc_account.data.borrow())
+                .unwrap()
+                .add_result,
+            1
+        );
+        assert_eq!(history_results(), [1].map(OperationResult::Unsigned));
+        for num1 in 2..=4 {
+            add(num1);
+        }
+        assert_eq!(history_results(), [2, 3, 4].map(OperationResult::Unsigned));
+
+        // Shrinking keeps the newest entries and refunds the payer
+        let payer_lamports = payer_account.lamports();
+        resize(2).unwrap();
+        let shrunk_rent = rent.minimum_balance(CalcResult::space(2));
+        assert_eq!(calc_account.data_len(), CalcResult::space(2));
+        assert_eq!(calc_account.lamports(), shrunk_rent);
+        assert_eq!(
+            payer_account.lamports(),
+            payer_lamports + grown_rent - shrunk_rent
+        );
+        assert_eq!(history_results(), [3, 4].map(OperationResult::Unsigned));
+        add(5);
+        assert_eq!(history_results(), [4, 5].map(OperationResult::Unsigned));
+
+        // Accounts can only grow by a limited amount at once
+        assert_eq!(resize(1_000), Err(ProgramError::InvalidRealloc));
+
+        // Only the authority may resize, with a signing payer
+        let mut other = TestAccount::new(0, &Pubkey::default());
+        let mut other_accounts = accounts.clone();
+        other_accounts[1] = other.info(true, false);
+        assert_eq!(
+            handle_instruction(
+                &program_id,
+                &other_accounts,
+                &CalculatorInstruction::Resize {
+                    history_capacity: 4
+                }
+                .pack()
+            ),
+            Err(CalculatorError::InvalidAuthority.into())
+        );
+        let mut unsigned_accounts = accounts.clone();
+        unsigned_accounts[2].is_signer = false;
+        assert_eq!(
+            handle_instruction(
+                &program_id,
+                &unsigned_accounts,
+                &CalculatorInstruction::Resize {
+                    history_capacity: 4
+                }
+                .pack()
+            ),
+            Err(ProgramError::MissingRequiredSignature)
+        );
+        assert_eq!(calc_account.data_len(), CalcResult::space(2));
+    }
+
+    #[test]
+    fn test_migrate() {
+        program_stubs::set_syscall_stubs(Box::new(TestSyscallStubs));
+        let program_id = CPI_PROGRAM_ID;
+        let rent = Rent::default();
+
+        // An account written by the original program
+        let mut calc = TestAccount::new(CalcResultV1::LEN, &program_id);
+        calc.lamports = rent.minimum_balance(CalcResultV1::LEN);
+        calc.data_mut()
+            .copy_from_slice(&[130u32.to_le_bytes(), 70u32.to_le_bytes()].concat());
+        let mut authority = TestAccount::new(0, &Pubkey::default());
+        let authority_key = authority.key;
+        let mut payer = TestAccount::new(0, &system_program::id());
+        payer.lamports = 1_000_000_000;
+        let mut system = TestAccount::new(0, &Pubkey::default());
+        system.key = system_program::id();
+
+        let calc_account = calc.info(true, true);
+        let accounts = [
+            calc_account.clone(),
+            authority.info(true, false),
+            payer.info(true, true),
+            system.info(false, false),
+        ];
+        let migrate = |accounts: &[AccountInfo]| {
+            handle_instruction(
+                &program_id,
+                accounts,
+                &CalculatorInstruction::Migrate.pack(),
+            )
+        };
+
+        // Old accounts must be migrated before use
+        let add_instruction_data = CalculatorInstruction::Add { num1: 1, num2: 2 }.pack();
+        assert_eq!(
+            handle_instruction(&program_id, &accounts[..2], &add_instruction_data),
+            Err(CalculatorError::UnsupportedAccountVersion.into())
+        );
+
+        // A third party cannot claim someone else's version 1 account and
+        // close it to take its lamports
+        let mut attacker = TestAccount::new(0, &Pubkey::default());
+        let mut attacker_accounts = accounts.clone();
+        attacker_accounts[0].is_signer = false;
+        attacker_accounts[1] = attacker.info(true, true);
+        assert_eq!(
+            migrate(&attacker_accounts),
+            Err(ProgramError::MissingRequiredSignature)
+        );
+        let close = [
+            attacker_accounts[0].clone(),
+            attacker_accounts[1].clone(),
+            attacker_accounts[1].clone(),
+        ];
+        assert_eq!(
+            handle_instruction(&program_id, &close, &CalculatorInstruction::Close.pack()),
+            Err(CalculatorError::UnsupportedAccountVersion.into())
+        );
+        assert_eq!(
+            calc_account.lamports(),
+            rent.minimum_balance(CalcResultV1::LEN)
+        );
+        assert_eq!(calc_account.data_len(), CalcResultV1::LEN);
+
+        migrate(&accounts).unwrap();
+        assert_eq!(calc_account.data_len(), CalcResult::LEN);
+        assert_eq!(
+            calc_account.lamports(),
+            rent.minimum_balance(CalcResult::LEN)
+        );
+        assert_eq!(
+            CalcResult::unpack(&calc_account.data.borrow()),
+            Ok(CalcResult {
+                add_result: 130,
+                sub_result: 70,
+                ..CalcResult::new(authority_key)
+            })
+        );
+        handle_instruction(&program_id, &accounts[..2], &add_instruction_data).unwrap();
+
+        // Migrating a current account changes nothing
+        let data = calc_account.data.borrow().to_vec();
+        migrate(&accounts).unwrap();
+        assert_eq!(*calc_account.data.borrow(), &data[..]);
+
+        // Later versions keep their authority
+        let v2_len = CalcResult::LEN - 3 * 8 - 3 * 4;
+        let mut calc = TestAccount::new(v2_len, &program_id);
+        calc.lamports = rent.minimum_balance(v2_len);
+        let mut v2 = CalcResult {
+            version: 2,
+            add_result: 5,
+            signed_sub_result: -5,
+            ..CalcResult::new(authority_key)
+        }
+        .try_to_vec()
+        .unwrap();
+        v2.truncate(v2_len);
+        calc.data_mut().copy_from_slice(&v2);
+        let mut other = TestAccount::new(0, &Pubkey::default());
+        let mut v2_accounts = accounts.clone();
+        v2_accounts[0] = calc.info(false, true);
+        v2_accounts[1] = other.info(true, false);
+        assert_eq!(
+            migrate(&v2_accounts),
+            Err(CalculatorError::InvalidAuthority.into())
+        );
+        v2_accounts[1] = accounts[1].clone();
+        migrate(&v2_accounts).unwrap();
+        assert_eq!(
+            CalcResult::unpack(&v2_accounts[0].data.borrow()),
+            Ok(CalcResult {
+                add_result: 5,
+                signed_sub_result: -5,
+                ..CalcResult::new(authority_key)
+            })
+        );
+
+        // Version 5 history entries are converted, oldest first, after the
+        // state grew in front of them
+        let v5_len = CalcResult::LEN - 8 - 16 - Decimal::LEN;
+        let v5_entry = |opcode: u8, operands: [i64; 2], result: i64, slot: u64| {
+            [
+                &[opcode][..],
+                &operands.map(i64::to_le_bytes).concat(),
+                &result.to_le_bytes(),
+                &slot.to_le_bytes(),
+                authority_key.as_ref(),
+            ]
+            .concat()
+        };
+        let mut v5 = CalcResult {
+            version: 5,
+            add_result: 3,
+            history_capacity: 2,
+            history_len: 2,
+            history_next: 1,
+            ..CalcResult::new(authority_key)
+        }
+        .try_to_vec()
+        .unwrap();
+        v5.truncate(v5_len);
+        v5.extend(v5_entry(5, [-1, 3], 2, 9));
+        v5.extend(v5_entry(0, [1, 2], 3, 8));
+        let mut calc = TestAccount::new(v5.len(), &program_id);
+        calc.lamports = rent.minimum_balance(v5.len());
+        calc.data_mut().copy_from_slice(&v5);
+        let mut v5_accounts = accounts.clone();
+        v5_accounts[0] = calc.info(false, true);
+        migrate(&v5_accounts).unwrap();
+        assert_eq!(v5_accounts[0].data_len(), CalcResult::space(2));
+        assert_eq!(
+            CalcResult::unpack(&v5_accounts[0].data.borrow()),
+            Ok(CalcResult {
+                add_result: 3,
+                history_capacity: 2,
+                history_len: 2,
+                history_next: 0,
+                ..CalcResult::new(authority_key)
+            })
+        );
+        let entry = |slot, result, instruction| HistoryEntry {
+            slot,
+            signer: authority_key,
+            result,
+            instruction,
+        };
+        assert_eq!(
+            decode_history(&v5_accounts[0].data.borrow()),
+            Ok(vec![
+                entry(
+                    8,
+                    OperationResult::Unsigned(3),
+                    CalculatorInstruction::Add { num1: 1, num2: 2 }
+                ),
+                entry(
+                    9,
+                    OperationResult::Signed(2),
+                    CalculatorInstruction::SignedAdd { num1: -1, num2: 3 }
+                ),
+            ])
+        );
+
+        // Version 6 entries keep their layout but move after the grown state
+        let v6_entry = entry(
+            8,
+            OperationResult::U64(3),
+            CalculatorInstruction::Arithmetic {
+                operation: Operation::Add,
+                operands: Operands::U64 { num1: 1, num2: 2 },
+            },
+        );
+        let mut v6 = CalcResult {
+            version: 6,
+            u64_result: 3,
+            history_capacity: 1,
+            history_len: 1,
+            ..CalcResult::new(authority_key)
+        }
+        .try_to_vec()
+        .unwrap();
+        v6.truncate(CalcResult::LEN - Decimal::LEN);
+        let mut slot = v6_entry.try_to_vec().unwrap();
+        slot.resize(HistoryEntry::LEN, 0);
+        v6.extend(slot);
+        let mut calc = TestAccount::new(v6.len(), &program_id);
+        calc.lamports = rent.minimum_balance(v6.len());
+        calc.data_mut().copy_from_slice(&v6);
+        let mut v6_accounts = accounts.clone();
+        v6_accounts[0] = calc.info(false, true);
+        migrate(&v6_accounts).unwrap();
+        assert_eq!(v6_accounts[0].data_len(), CalcResult::space(1));
+        assert_eq!(
+            CalcResult::unpack(&v6_accounts[0].data.borrow())
+                .unwrap()
+                .u64_result,
+            3
+        );
+        assert_eq!(
+            decode_history(&v6_accounts[0].data.borrow()),
+            Ok(vec![v6_entry])
+        );
+
+        // A history whose converted entries would grow the account beyond
+        // one reallocation keeps the newest entries that fit
+        let capacity = 200;
+        let mut v5 = CalcResult {
+            version: 5,
+            history_capacity: capacity,
+            history_len: capacity,
+            history_next: 0,
+            ..CalcResult::new(authority_key)
+        }
+        .try_to_vec()
+        .unwrap();
+        v5.truncate(v5_len);
+        for slot in 0..capacity {
+            let num = i64::from(slot);
+            v5.extend(v5_entry(0, [num, num], 2 * num, slot.into()));
+        }
+        assert!(CalcResult::space(capacity) > v5.len() + MAX_PERMITTED_DATA_INCREASE);
+        let mut calc = TestAccount::new(v5.len(), &program_id);
+        calc.lamports = rent.minimum_balance(v5.len());
+        calc.data_mut().copy_from_slice(&v5);
+        let mut v5_accounts = accounts.clone();
+        v5_accounts[0] = calc.info(false, true);
+        migrate(&v5_accounts).unwrap();
+        let kept =
+            ((v5.len() + MAX_PERMITTED_DATA_INCREASE - CalcResult::LEN) / HistoryEntry::LEN) as u32;
+        assert_eq!(v5_accounts[0].data_len(), CalcResult::space(kept));
+        let state = CalcResult::unpack(&v5_accounts[0].data.borrow()).unwrap();
+        assert_eq!(state.history_capacity, kept);
+        assert_eq!(state.history_len, kept);
+        let history = decode_history(&v5_accounts[0].data.borrow()).unwrap();
+        assert_eq!(
+            history.iter().map(|entry| entry.slot).collect::<Vec<_>>(),
+            (u64::from(capacity - kept)..capacity.into()).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_memory_registers() {
+        program_stubs::set_syscall_stubs(Box::new(TestSyscallStubs));
+        let program_id = CPI_PROGRAM_ID;
+        let mut calc = TestAccount::new(CalcResult::LEN, &program_id);
+        let mut authority = TestAccount::new(0, &Pubkey::default());
+        let accounts = vec![calc.info(false, true), authority.info(true, false)];
+        initialize(&program_id, &accounts);
+
+        let run = |instruction: CalculatorInstruction| {
+            handle_instruction(&program_id, &accounts, &instruction.pack())?;
+            Ok::<_, ProgramError>(OperationResult::from_return_data(&program_id).unwrap())
+        };
+        let memory = || {
+            CalcResult::unpack(&accounts[0].data.borrow())
+                .unwrap()
+                .memory
+        };
+
+        // 100 + 30 M+ 10 - 25 M- MR
+        run(CalculatorInstruction::Add {
+            num1: 100,
+            num2: 30,
+        })
+        .unwrap();
+        assert_eq!(
+            run(CalculatorInstruction::MemoryAdd),
+            Ok(OperationResult::Signed(130))
+        );
+        run(CalculatorInstruction::SignedSubtract { num1: 10, num2: 25 }).unwrap();
+        assert_eq!(
+            run(CalculatorInstruction::MemorySubtract),
+            Ok(OperationResult::Signed(145))
+        );
+        assert_eq!(
+            run(CalculatorInstruction::MemoryRecall),
+            Ok(OperationResult::Signed(145))
+        );
+        assert_eq!(
+            CalcResult::unpack(&accounts[0].data.borrow())
+                .unwrap()
+                .last_result,
+            145
+        );
+
+        // The recalled value is the last result, so M+ doubles the memory
+        assert_eq!(
+            run(CalculatorInstruction::MemoryAdd),
+            Ok(OperationResult::Signed(290))
+        );
+
+        // MR loads the memory into the accumulator, so a running total
+        // continues from it without the client reading and resending it:
+        // 2 + 3 M+ 4 + 5 M+ MR + 10
+        run(CalculatorInstruction::MemoryClear).unwrap();
+        run(CalculatorInstruction::AccumulatorSet { value: -7 }).unwrap();
+        run(CalculatorInstruction::Add { num1: 2, num2: 3 }).unwrap();
+        run(CalculatorInstruction::MemoryAdd).unwrap();
+        run(CalculatorInstruction::Add { num1: 4, num2: 5 }).unwrap();
+        run(CalculatorInstruction::MemoryAdd).unwrap();
+        assert_eq!(
+            run(CalculatorInstruction::MemoryRecall),
+            Ok(OperationResult::Signed(14))
+        );
+        assert_eq!(
+            run(CalculatorInstruction::AccumulatorAdd { num: 10 }),
+            Ok(OperationResult::Signed(24))
+        );
+        assert_eq!(
+            CalcResult::unpack(&accounts[0].data.borrow())
+                .unwrap()
+                .value,
+            24
+        );
+
+        assert_eq!(
+            run(CalculatorInstruction::MemoryClear),
+            Ok(OperationResult::Signed(0))
+        );
+        assert_eq!(memory(), 0);
+
+        // The memory register is range checked
+        run(CalculatorInstruction::SignedAdd {
+            num1: i64::MIN,
+            num2: 0,
+        })
+        .unwrap();
+        assert_eq!(
+            run(CalculatorInstruction::MemorySubtract),
+            Err(CalculatorError::Overflow.into())
+        );
+        run(CalculatorInstruction::MemoryAdd).unwrap();
+        assert_eq!(
+            run(CalculatorInstruction::MemoryAdd),
+            Err(CalculatorError::Underflow.into())
+        );
+        assert_eq!(memory(), i64::MIN);
+
+        // Memory instructions need a calculator account
+        assert_eq!(
+            handle_instruction(
+                &program_id,
+                &[],
+                &CalculatorInstruction::MemoryRecall.pack()
+            ),
+            Err(ProgramError::NotEnoughAccountKeys)
+        );
+    }
+
+    #[test]
+    fn test_accumulator() {
+        program_stubs::set_syscall_stubs(Box::new(TestSyscallStubs));
+        let program_id = CPI_PROGRAM_ID;
+        let mut calc = TestAccount::new(CalcResult::LEN, &program_id);
+        let mut authority = TestAccount::new(0, &Pubkey::default());
+        let accounts = vec![calc.info(false, true), authority.info(true, false)];
+        initialize(&program_id, &accounts);
+
+        let run = |instruction: CalculatorInstruction| {
+            handle_instruction(&program_id, &accounts, &instruction.pack())?;
+            Ok::<_, ProgramError>(OperationResult::from_return_data(&program_id).unwrap())
+        };
+        let value = || {
+            CalcResult::unpack(&accounts[0].data.borrow())
+                .unwrap()
+                .value
+        };
+
+        // Each instruction updates the running value of the previous ones
+        let steps = [
+            (CalculatorInstruction::AccumulatorAdd { num: 10 }, 10),
+            (CalculatorInstruction::AccumulatorAdd { num: 5 }, 15),
+            (CalculatorInstruction::AccumulatorSubtract { num: 20 }, -5),
+            (CalculatorInstruction::AccumulatorMultiply { num: -6 }, 30),
+            (CalculatorInstruction::AccumulatorDivide { num: 4 }, 7),
+            (CalculatorInstruction::AccumulatorDivide { num: -2 }, -3),
+        ];
+        for (instruction, expected) in steps {
+            assert_eq!(run(instruction), Ok(OperationResult::Signed(expected)));
+            assert_eq!(value(), expected);
+        }
+
+        // The value is the last result for the memory register
+        assert_eq!(
+            run(CalculatorInstruction::MemoryAdd),
+            Ok(OperationResult::Signed(-3))
+        );
+
+        // Failed steps leave the value untouched
+        assert_eq!(
+            run(CalculatorInstruction::AccumulatorDivide { num: 0 }),
+            Err(CalculatorError::DivideByZero.into())
+        );
+        assert_eq!(
+            run(CalculatorInstruction::AccumulatorMultiply { num: i64::MAX }),
+            Err(CalculatorError::Underflow.into())
+        );
+        assert_eq!(
+            run(CalculatorInstruction::AccumulatorSubtract { num: i64::MAX }),
+            Err(CalculatorError::Underflow.into())
+        );
+        assert_eq!(value(), -3);
+
+        assert_eq!(
+            run(CalculatorInstruction::AccumulatorSet { value: i64::MIN }),
+            Ok(OperationResult::Signed(i64::MIN))
+        );
+        assert_eq!(
+            run(CalculatorInstruction::AccumulatorDivide { num: -1 }),
+            Err(CalculatorError::Overflow.into())
+        );
+        assert_eq!(
+            run(CalculatorInstruction::AccumulatorSet { value: 0 }),
+            Ok(OperationResult::Signed(0))
+        );
+
+        // Only the authority may update the value
+        let mut other = TestAccount::new(0, &Pubkey::default());
+        let other_accounts = vec![accounts[0].clone(), other.info(true, false)];
+        assert_eq!(
+            handle_instruction(
+                &program_id,
+                &other_accounts,
+                &CalculatorInstruction::AccumulatorAdd { num: 1 }.pack()
+            ),
+            Err(CalculatorError::InvalidAuthority.into())
+        );
+    }
+
+    #[test]
+    fn test_history() {
+        program_stubs::set_syscall_stubs(Box::new(TestSyscallStubs));
+        let program_id = CPI_PROGRAM_ID;
+        let mut calc = TestAccount::new(CalcResult::space(2), &program_id);
+        let mut authority = TestAccount::new(0, &Pubkey::default());
+        let authority_key = authority.key;
+        let accounts = vec![calc.info(false, true), authority.info(true, false)];
+
+        // The account must be large enough for the requested history
+        assert_eq!(
+            try_initialize(&program_id, &accounts, 3),
+            Err(ProgramError::AccountDataTooSmall)
+        );
+        try_initialize(&program_id, &accounts, 2).unwrap();
+
+        let instructions = [
+            CalculatorInstruction::Add { num1: 1, num2: 2 },
+            CalculatorInstruction::SignedSubtract { num1: 3, num2: 5 },
+            CalculatorInstruction::MemoryAdd,
+        ];
+        for instruction in &instructions {
+            handle_instruction(&program_id, &accounts, &instruction.pack()).unwrap();
+        }
+
+        // Failed operations and other instructions are not recorded
+        assert!(handle_instruction(
+            &program_id,
+            &accounts,
+            &CalculatorInstruction::Divide { num1: 1, num2: 0 }.pack()
+        )
+        .is_err());
+        handle_instruction(
+            &program_id,
+            &accounts,
+            &CalculatorInstruction::SetAuthority {
+                new_authority: authority_key,
+            }
+            .pack(),
+        )
+        .unwrap();
+
+        // Only the last two operations fit, oldest first
+        let entry = |instruction: &CalculatorInstruction, result| HistoryEntry {
+            slot: TEST_SLOT,
+            signer: authority_key,
+            result: OperationResult::Signed(result),
+            instruction: instruction.clone(),
+        };
+        assert_eq!(
+            decode_history(&accounts[0].data.borrow()),
+            Ok(vec![
+                entry(&instructions[1], -2),
+                entry(&instructions[2], -2),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_operand_widths() {
+        program_stubs::set_syscall_stubs(Box::new(TestSyscallStubs));
+        let program_id = CPI_PROGRAM_ID;
+        let mut calc = TestAccount::new(CalcResult::space(1), &program_id);
+        let mut authority = TestAccount::new(0, &Pubkey::default());
+        let authority_key = authority.key;
+        let accounts = vec![calc.info(false, true), authority.info(true, false)];
+        try_initialize(&program_id, &accounts, 1).unwrap();
+
+        let run = |operation, operands| {
+            let instruction = CalculatorInstruction::Arithmetic {
+                operation,
+                operands,
+            };
+            handle_instruction(&program_id, &accounts, &instruction.pack())?;
+            Ok::<_, ProgramError>(OperationResult::from_return_data(&program_id).unwrap())
+        };
+        let state = || CalcResult::unpack(&accounts[0].data.borrow()).unwrap();
+
+        // `u32` operands behave like the dedicated instructions
+        assert_eq!(
+            run(Operation::Divide, Operands::U32 { num1: 7, num2: 2 }),
+            Ok(OperationResult::Unsigned(3))
+        );
+        assert_eq!(state().div_result, 3);
+        assert_eq!(state().last_result, 3);
+
+        // Wide results go to their own fields and leave the last result alone
+        assert_eq!(
+            run(
+                Operation::Multiply,
+                Operands::U64 {
+                    num1: u32::MAX.into(),
+                    num2: 4
+                }
+            ),
+            Ok(OperationResult::U64(u64::from(u32::MAX) * 4))
+        );
+        assert_eq!(state().u64_result, u64::from(u32::MAX) * 4);
+        assert_eq!(
+            run(
+                Operation::Add,
+                Operands::U128 {
+                    num1: u64::MAX.into(),
+                    num2: u64::MAX.into()
+                }
+            ),
+            Ok(OperationResult::U128(u128::from(u64::MAX) * 2))
+        );
+        assert_eq!(state().u128_result, u128::from(u64::MAX) * 2);
+        assert_eq!(state().mul_result, 0);
+        assert_eq!(state().last_result, 3);
+
+        // The history records wide operands and results in full
+        assert_eq!(
+            decode_history(&accounts[0].data.borrow()),
+            Ok(vec![HistoryEntry {
+                slot: TEST_SLOT,
+                signer: authority_key,
+                result: OperationResult::U128(u128::from(u64::MAX) * 2),
+                instruction: CalculatorInstruction::Arithmetic {
+                    operation: Operation::Add,
+                    operands: Operands::U128 {
+                        num1: u64::MAX.into(),
+                        num2: u64::MAX.into()
+                    },
+                },
+            }])
+        );
+
+        // Every width checks its own bounds
+        let cases = [
+            (
+                Operation::Add,
+                Operands::U64 {
+                    num1: u64::MAX,
+                    num2: 1,
+                },
+                CalculatorError::Overflow,
+            ),
+            (
+                Operation::Subtract,
+                Operands::U128 { num1: 0, num2: 1 },
+                CalculatorError::Underflow,
+            ),
+            (
+                Operation::Multiply,
+                Operands::U128 {
+                    num1: u128::MAX,
+                    num2: 2,
+                },
+                CalculatorError::Overflow,
+            ),
+            (
+                Operation::Remainder,
+                Operands::U64 { num1: 1, num2: 0 },
+                CalculatorError::DivideByZero,
+            ),
+            (
+                Operation::Divide,
+                Operands::U32 { num1: 1, num2: 0 },
+                CalculatorError::DivideByZero,
+            ),
+        ];
+        for (operation, operands, error) in cases {
+            assert_eq!(run(operation, operands), Err(error.into()));
+        }
+        assert_eq!(state().u64_result, u64::from(u32::MAX) * 4);
+
+        // Wide operations can be computed without an account
+        let instruction = CalculatorInstruction::Arithmetic {
+            operation: Operation::Subtract,
+            operands: Operands::U128 {
+                num1: u128::MAX,
+                num2: 1,
+            },
+        };
+        handle_instruction(&program_id, &[], &instruction.pack()).unwrap();
+        assert_eq!(
+            OperationResult::from_return_data(&program_id),
+            Some(OperationResult::U128(u128::MAX - 1))
+        );
+    }
+
+    #[test]
+    fn test_decimal_arithmetic() {
+        program_stubs::set_syscall_stubs(Box::new(TestSyscallStubs));
+        let program_id = CPI_PROGRAM_ID;
+        let mut calc = TestAccount::new(CalcResult::LEN, &program_id);
+        let mut authority = TestAccount::new(0, &Pubkey::default());
+        let accounts = vec![calc.info(false, true), authority.info(true, false)];
+        initialize(&program_id, &accounts);
+
+        // 1.5 tokens at 18 decimals divided by 4, at 6 decimals
+        let instruction = CalculatorInstruction::DecimalArithmetic {
+            operation: Operation::Divide,
+            num1: Decimal::new(15 * 10u128.pow(17), 18),
+            num2: Decimal::new(4, 0),
+            scale: 6,
+            rounding: Rounding::Floor,
+        };
+        handle_instruction(&program_id, &accounts, &instruction.pack()).unwrap();
+        let expected = Decimal::new(375_000, 6);
+        assert_eq!(
+            OperationResult::from_return_data(&program_id),
+            Some(OperationResult::Decimal(expected))
+        );
+        let state = CalcResult::unpack(&accounts[0].data.borrow()).unwrap();
+        assert_eq!(state.decimal_result, expected);
+        assert_eq!(state.decimal_result.to_string(), "0.375000");
+        assert_eq!(state.last_result, 0);
+
+        // Errors leave the stored result unchanged
+        let instruction = CalculatorInstruction::DecimalArithmetic {
+            operation: Operation::Add,
+            num1: Decimal::new(1, 39),
+            num2: Decimal::new(1, 0),
+            scale: 0,
+            rounding: Rounding::Ceil,
+        };
+        assert_eq!(
+            handle_instruction(&program_id, &accounts, &instruction.pack()),
+            Err(CalculatorError::InvalidScale.into())
+        );
+        assert_eq!(
+            CalcResult::unpack(&accounts[0].data.borrow())
+                .unwrap()
+                .decimal_result,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_rounded_division() {
+        program_stubs::set_syscall_stubs(Box::new(TestSyscallStubs));
+        let program_id = CPI_PROGRAM_ID;
+        let mut calc = TestAccount::new(CalcResult::LEN, &program_id);
+        let mut authority = TestAccount::new(0, &Pubkey::default());
+        let accounts = vec![calc.info(false, true), authority.info(true, false)];
+        initialize(&program_id, &accounts);
+
+        let run = |instruction: CalculatorInstruction| {
+            handle_instruction(&program_id, &accounts, &instruction.pack())?;
+            Ok::<_, ProgramError>(OperationResult::from_return_data(&program_id).unwrap())
+        };
+        let state = || CalcResult::unpack(&accounts[0].data.borrow()).unwrap();
+
+        // A fee of 10 / 4 rounded each way, at every width
+        let expected = [
+            (Rounding::Floor, 2),
+            (Rounding::Ceil, 3),
+            (Rounding::HalfUp, 3),
+            (Rounding::HalfEven, 2),
+        ];
+        for (rounding, result) in expected {
+            let divide = |operands| CalculatorInstruction::DivideRounded { operands, rounding };
+            assert_eq!(
+                run(divide(Operands::U32 { num1: 10, num2: 4 })),
+                Ok(OperationResult::Unsigned(result))
+            );
+            assert_eq!(state().div_result, result);
+            assert_eq!(state().last_result, i64::from(result));
+            assert_eq!(
+                run(divide(Operands::U64 { num1: 10, num2: 4 })),
+                Ok(OperationResult::U64(result.into()))
+            );
+            assert_eq!(state().u64_result, u64::from(result));
+            assert_eq!(
+                run(divide(Operands::U128 { num1: 10, num2: 4 })),
+                Ok(OperationResult::U128(result.into()))
+            );
+            assert_eq!(state().u128_result, u128::from(result));
+        }
+        assert_eq!(
+            run(CalculatorInstruction::DivideRounded {
+                operands: Operands::U64 { num1: 1, num2: 0 },
+                rounding: Rounding::Ceil,
+            }),
+            Err(CalculatorError::DivideByZero.into())
+        );
+
+        // The accumulator rounds signed values, -10 / 4 being -2.5
+        let expected = [
+            (Rounding::Floor, -3),
+            (Rounding::Ceil, -2),
+            (Rounding::HalfUp, -3),
+            (Rounding::HalfEven, -2),
+        ];
+        for (rounding, value) in expected {
+            run(CalculatorInstruction::AccumulatorSet { value: -10 }).unwrap();
+            assert_eq!(
+                run(CalculatorInstruction::AccumulatorDivideRounded { num: 4, rounding }),
+                Ok(OperationResult::Signed(value))
+            );
+            assert_eq!(state().value, value);
+        }
+        run(CalculatorInstruction::AccumulatorSet { value: i64::MIN }).unwrap();
+        let divide = |num| CalculatorInstruction::AccumulatorDivideRounded {
+            num,
+            rounding: Rounding::HalfEven,
+        };
+        assert_eq!(run(divide(-1)), Err(CalculatorError::Overflow.into()));
+        assert_eq!(run(divide(0)), Err(CalculatorError::DivideByZero.into()));
+        assert_eq!(state().value, i64::MIN);
+    }
+
+    #[test]
+    fn test_mul_div() {
+        program_stubs::set_syscall_stubs(Box::new(TestSyscallStubs));
+        let program_id = CPI_PROGRAM_ID;
+        let mut calc = TestAccount::new(CalcResult::LEN, &program_id);
+        let mut authority = TestAccount::new(0, &Pubkey::default());
+        let accounts = vec![calc.info(false, true), authority.info(true, false)];
+        initialize(&program_id, &accounts);
+
+        let run = |operands, rounding| {
+            let instruction = CalculatorInstruction::MulDiv { operands, rounding };
+            handle_instruction(&program_id, &accounts, &instruction.pack())?;
+            Ok::<_, ProgramError>(OperationResult::from_return_data(&program_id).unwrap())
+        };
+        let state = || CalcResult::unpack(&accounts[0].data.borrow()).unwrap();
+
+        // A 0.3% fee on an amount whose product with the rate overflows a `u64`
+        let amount = u64::MAX / 2;
+        let fee = |rounding| {
+            let operands = MulDivOperands::U64 {
+                num1: amount,
+                num2: 30,
+                denominator: 10_000,
+            };
+            run(operands, rounding)
+        };
+        let floor = (u128::from(amount) * 30 / 10_000) as u64;
+        assert_eq!(fee(Rounding::Floor), Ok(OperationResult::U64(floor)));
+        assert_eq!(state().u64_result, floor);
+        assert_eq!(fee(Rounding::Ceil), Ok(OperationResult::U64(floor + 1)));
+        assert_eq!(state().last_result, 0);
+
+        // A pro-rata share of a `u128` pool, with a 256-bit product
+        let operands = MulDivOperands::U128 {
+            num1: u128::MAX,
+            num2: 2,
+            denominator: 3,
+        };
+        let share = u128::MAX / 3 * 2;
+        assert_eq!(
+            run(operands, Rounding::HalfEven),
+            Ok(OperationResult::U128(share))
+        );
+        assert_eq!(state().u128_result, share);
+
+        // Errors
+        let operands = MulDivOperands::U64 {
+            num1: u64::MAX,
+            num2: 2,
+            denominator: 1,
+        };
+        assert_eq!(
+            run(operands, Rounding::Floor),
+            Err(CalculatorError::Overflow.into())
+        );
+        let operands = MulDivOperands::U128 {
+            num1: 1,
+            num2: 1,
+            denominator: 0,
+        };
+        assert_eq!(
+            run(operands, Rounding::Floor),
+            Err(CalculatorError::DivideByZero.into())
+        );
+        assert_eq!(state().u128_result, share);
+    }
+
+    #[test]
+    fn test_powers_and_roots() {
+        program_stubs::set_syscall_stubs(Box::new(TestSyscallStubs));
+        let program_id = CPI_PROGRAM_ID;
+        let mut calc = TestAccount::new(CalcResult::LEN, &program_id);
+        let mut authority = TestAccount::new(0, &Pubkey::default());
+        let accounts = vec![calc.info(false, true), authority.info(true, false)];
+        initialize(&program_id, &accounts);
+
+        let run = |instruction: CalculatorInstruction| {
+            handle_instruction(&program_id, &accounts, &instruction.pack())?;
+            Ok::<_, ProgramError>(OperationResult::from_return_data(&program_id).unwrap())
+        };
+        let state = || CalcResult::unpack(&accounts[0].data.borrow()).unwrap();
+
+        assert_eq!(
+            run(CalculatorInstruction::Pow {
+                base: Operand::U64(10),
+                exponent: 18,
+            }),
+            Ok(OperationResult::U64(10u64.pow(18)))
+        );
+        assert_eq!(state().u64_result, 10u64.pow(18));
+        assert_eq!(state().last_result, 0);
+        assert_eq!(
+            run(CalculatorInstruction::Pow {
+                base: Operand::U128(2),
+                exponent: 127,
+            }),
+            Ok(OperationResult::U128(1 << 127))
+        );
+        assert_eq!(state().u128_result, 1 << 127);
+
+        assert_eq!(
+            run(CalculatorInstruction::Isqrt {
+                value: Operand::U64(99),
+            }),
+            Ok(OperationResult::U64(9))
+        );
+        assert_eq!(
+            run(CalculatorInstruction::Isqrt {
+                value: Operand::U128(u128::MAX),
+            }),
+            Ok(OperationResult::U128(u64::MAX.into()))
+        );
+        assert_eq!(
+            run(CalculatorInstruction::NthRoot {
+                value: Operand::U64(10u64.pow(18)),
+                degree: 6,
+            }),
+            Ok(OperationResult::U64(1_000))
+        );
+        assert_eq!(state().u64_result, 1_000);
+        assert_eq!(
+            run(CalculatorInstruction::NthRoot {
+                value: Operand::U128(1 << 90),
+                degree: 3,
+            }),
+            Ok(OperationResult::U128(1 << 30))
+        );
+
+        // Errors leave the results unchanged
+        assert_eq!(
+            run(CalculatorInstruction::Pow {
+                base: Operand::U64(10),
+                exponent: 20,
+            }),
+            Err(CalculatorError::Overflow.into())
+        );
+        assert_eq!(
+            run(CalculatorInstruction::NthRoot {
+                value: Operand::U128(8),
+                degree: 0,
+            }),
+            Err(CalculatorError::InvalidDegree.into())
+        );
+        assert_eq!(state().u64_result, 1_000);
+        assert_eq!(state().u128_result, 1 << 30);
+    }
+
+    #[test]
+    fn test_compute_only() {
+        program_stubs::set_syscall_stubs(Box::new(TestSyscallStubs));
+        let program_id = CPI_PROGRAM_ID;
+
+        handle_instruction(
+            &program_id,
+            &[],
+            &CalculatorInstruction::Multiply { num1: 6, num2: 7 }.pack(),
+        )
+        .unwrap();
+        assert_eq!(
+            OperationResult::from_return_data(&program_id),
+            Some(OperationResult::Unsigned(42))
+        );
+
+        handle_instruction(
+            &program_id,
+            &[],
+            &CalculatorInstruction::SignedSubtract { num1: 3, num2: 5 }.pack(),
+        )
+        .unwrap();
+        assert_eq!(
+            OperationResult::from_return_data(&program_id),
+            Some(OperationResult::Signed(-2))
+        );
+
+        // Errors are still reported
+        assert_eq!(
+            handle_instruction(
+                &program_id,
+                &[],
+                &CalculatorInstruction::Divide { num1: 1, num2: 0 }.pack()
+            ),
+            Err(CalculatorError::DivideByZero.into())
+        );
+
+        // Instructions that manage accounts still require them
+        assert_eq!(
+            handle_instruction(&program_id, &[], &CalculatorInstruction::Close.pack()),
+            Err(ProgramError::NotEnoughAccountKeys)
+        );
+    }
+}
@@ -0,0 +1,804 @@
+use crate::{
+    decimal::{Decimal, PodDecimal},
+    error::CalculatorError,
+    instruction::{CalculatorInstruction, OperationResult},
+    pod::{PodBool, PodI64, PodU128, PodU32, PodU64},
+};
+use borsh::{BorshDeserialize, BorshSerialize};
+use bytemuck::{Pod, Zeroable};
+use solana_program::{clock::Slot, msg, program_error::ProgramError, pubkey::Pubkey};
+
+/// Seed prefix of program-derived calculator account addresses
+pub const CALCULATOR_SEED: &[u8] = b"calculator";
+
+/// Derive the address of `user`'s calculator account with the given label,
+/// which may be empty
+pub fn find_calculator_address(program_id: &Pubkey, user: &Pubkey, label: &str) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[CALCULATOR_SEED, user.as_ref(), label.as_bytes()],
+        program_id,
+    )
+}
+
+/// Define the type of state stored in accounts
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default, PartialEq, Eq)]
+pub struct CalcResult {
+    /// Account type tag, always `CalcResult::DISCRIMINATOR` once initialized
+    pub discriminator: [u8; 8],
+    /// Layout version the account data was written with
+    pub version: u8,
+    /// Whether the account has been set up by `Initialize`
+    pub is_initialized: bool,
+    /// Key that must sign every instruction modifying the account
+    pub authority: Pubkey,
+    /// Result of the addition operation
+    pub add_result: u32,
+    /// Result of the subtraction operation
+    pub sub_result: u32,
+    /// Result of the multiplication operation
+    pub mul_result: u32,
+    /// Quotient of the division operation
+    pub div_result: u32,
+    /// Remainder of the remainder operation
+    pub rem_result: u32,
+    /// Result of the signed addition operation
+    pub signed_add_result: i64,
+    /// Result of the signed subtraction operation
+    pub signed_sub_result: i64,
+    /// Result of the most recent arithmetic operation or memory recall, the
+    /// value the memory instructions work with
+    pub last_result: i64,
+    /// The memory register
+    pub memory: i64,
+    /// Running value updated by the accumulator instructions
+    pub value: i64,
+    /// Number of entries the history ring buffer after the state can hold
+    pub history_capacity: u32,
+    /// Number of entries recorded in the history, at most its capacity
+    pub history_len: u32,
+    /// Index of the history slot the next entry is written to
+    pub history_next: u32,
+    /// Result of the last operation on `u64` operands
+    pub u64_result: u64,
+    /// Result of the last operation on `u128` operands
+    pub u128_result: u128,
+    /// Result of the last fixed-point decimal operation
+    pub decimal_result: Decimal,
+}
+
+impl CalcResult {
+    /// Size of the Borsh-encoded state in bytes
+    pub const LEN: usize = 8 + 1 + 1 + 32 + 5 * 4 + 5 * 8 + 3 * 4 + 8 + 16 + Decimal::LEN;
+
+    /// Tag identifying calculator accounts
+    pub const DISCRIMINATOR: [u8; 8] = *b"calcrslt";
+
+    /// Current layout version; version 1 was the original header-less layout,
+    /// versions 2 to 4 lacked the memory register, accumulator and history,
+    /// version 5 lacked the wide results and recorded `i64` history entries and
+    /// version 6 lacked the decimal result
+    pub const VERSION: u8 = 7;
+
+    /// Create freshly initialized state with all results zeroed
+    pub fn new(authority: Pubkey) -> Self {
+        Self {
+            discriminator: Self::DISCRIMINATOR,
+            version: Self::VERSION,
+            is_initialized: true,
+            authority,
+            ..Self::default()
+        }
+    }
+
+    /// Decode initialized calculator state from account data, rejecting
+    /// uninitialized and foreign-typed accounts and accounts that still use
+    /// an older layout
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        match VersionedCalcResult::unpack(data)? {
+            VersionedCalcResult::Current(state) => Ok(state),
+            _ => {
+                msg!("Calculator account uses an outdated layout and must be migrated");
+                Err(CalculatorError::UnsupportedAccountVersion.into())
+            }
+        }
+    }
+
+    /// Encoded length of the state written by layout `version`, for the
+    /// versions with a header
+    fn layout_len(version: u8) -> Option<usize> {
+        match version {
+            2 => Some(8 + 1 + 1 + 32 + 5 * 4 + 2 * 8),
+            3 => Some(8 + 1 + 1 + 32 + 5 * 4 + 4 * 8),
+            4 => Some(8 + 1 + 1 + 32 + 5 * 4 + 5 * 8),
+            5 => Some(8 + 1 + 1 + 32 + 5 * 4 + 5 * 8 + 3 * 4),
+            6 => Some(8 + 1 + 1 + 32 + 5 * 4 + 5 * 8 + 3 * 4 + 8 + 16),
+            Self::VERSION => Some(Self::LEN),
+            _ => None,
+        }
+    }
+
+    /// Encode the state into the start of the account data
+    pub fn pack(&self, data: &mut [u8]) -> Result<(), ProgramError> {
+        self.serialize(&mut &mut data[..])?;
+        Ok(())
+    }
+
+    /// Account data size needed for the state and a history of
+    /// `history_capacity` entries
+    pub fn space(history_capacity: u32) -> usize {
+        Self::LEN + history_capacity as usize * HistoryEntry::LEN
+    }
+
+    /// Whether the account data already holds initialized state of any version
+    pub fn is_initialized(data: &[u8]) -> bool {
+        data.len() > 9 && data[..8] == Self::DISCRIMINATOR && data[9] != 0
+    }
+}
+
+/// Zero-copy view of `CalcResult` at the start of account data
+///
+/// The fields are byte-aligned and laid out like the Borsh encoding, so
+/// instructions read and write them in place instead of decoding and
+/// re-encoding the whole state.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Pod, Zeroable)]
+#[repr(C)]
+pub struct PodCalcResult {
+    /// Account type tag, always `CalcResult::DISCRIMINATOR` once initialized
+    pub discriminator: [u8; 8],
+    /// Layout version the account data was written with
+    pub version: u8,
+    /// Whether the account has been set up by `Initialize`
+    pub is_initialized: PodBool,
+    /// Key that must sign every instruction modifying the account
+    pub authority: Pubkey,
+    /// Result of the addition operation
+    pub add_result: PodU32,
+    /// Result of the subtraction operation
+    pub sub_result: PodU32,
+    /// Result of the multiplication operation
+    pub mul_result: PodU32,
+    /// Quotient of the division operation
+    pub div_result: PodU32,
+    /// Remainder of the remainder operation
+    pub rem_result: PodU32,
+    /// Result of the signed addition operation
+    pub signed_add_result: PodI64,
+    /// Result of the signed subtraction operation
+    pub signed_sub_result: PodI64,
+    /// Result of the most recent arithmetic operation or memory recall
+    pub last_result: PodI64,
+    /// The memory register
+    pub memory: PodI64,
+    /// Running value updated by the accumulator instructions
+    pub value: PodI64,
+    /// Number of entries the history ring buffer after the state can hold
+    pub history_capacity: PodU32,
+    /// Number of entries recorded in the history, at most its capacity
+    pub history_len: PodU32,
+    /// Index of the history slot the next entry is written to
+    pub history_next: PodU32,
+    /// Result of the last operation on `u64` operands
+    pub u64_result: PodU64,
+    /// Result of the last operation on `u128` operands
+    pub u128_result: PodU128,
+    /// Result of the last fixed-point decimal operation
+    pub decimal_result: PodDecimal,
+}
+
+impl PodCalcResult {
+    /// View initialized calculator state of the current version in account
+    /// data, along with the history buffer that follows it
+    pub fn load(data: &[u8]) -> Result<(&Self, &[u8]), ProgramError> {
+        Self::check(data)?;
+        let (state, history) = data.split_at(CalcResult::LEN);
+        Ok((bytemuck::from_bytes(state), history))
+    }
+
+    /// Mutable version of `load`
+    pub fn load_mut(data: &mut [u8]) -> Result<(&mut Self, &mut [u8]), ProgramError> {
+        Self::check(data)?;
+        let (state, history) = data.split_at_mut(CalcResult::LEN);
+        Ok((bytemuck::from_bytes_mut(state), history))
+    }
+
+    // Accept the accounts `CalcResult::unpack` accepts, leaving the reporting
+    // of why anything else is rejected to it
+    fn check(data: &[u8]) -> Result<(), ProgramError> {
+        let is_current = data
+            .get(..CalcResult::LEN)
+            .map(bytemuck::from_bytes::<Self>)
+            .map_or(false, |state| {
+                state.discriminator == CalcResult::DISCRIMINATOR
+                    && state.is_initialized == PodBool::from(true)
+                    && state.version == CalcResult::VERSION
+            });
+        if is_current {
+            Ok(())
+        } else {
+            CalcResult::unpack(data).and(Err(CalculatorError::InvalidAccountType.into()))
+        }
+    }
+
+    /// Record `entry` in the history ring buffer, overwriting the oldest
+    /// entry once it is full
+    ///
+    /// Does nothing for accounts initialized without a history.
+    pub fn push_history(
+        &mut self,
+        history: &mut [u8],
+        entry: &HistoryEntry,
+    ) -> Result<(), ProgramError> {
+        let capacity = u32::from(self.history_capacity);
+        if capacity == 0 {
+            return Ok(());
+        }
+
+        let next = u32::from(self.history_next);
+        let offset = next as usize * HistoryEntry::LEN;
+        let slot = history
+            .get_mut(offset..offset + HistoryEntry::LEN)
+            .ok_or(ProgramError::AccountDataTooSmall)?;
+        slot.fill(0);
+        entry.serialize(&mut &mut slot[..])?;
+
+        self.history_next = ((next + 1) % capacity).into();
+        self.history_len = (u32::from(self.history_len) + 1).min(capacity).into();
+        Ok(())
+    }
+
+    /// Decode the recorded history, oldest entry first
+    pub fn history(&self, history: &[u8]) -> Result<Vec<HistoryEntry>, ProgramError> {
+        history_slots(
+            self.history_capacity.into(),
+            self.history_len.into(),
+            self.history_next.into(),
+            HistoryEntry::LEN,
+            history,
+        )?
+        .map(|mut slot| {
+            // Entries are followed by the zero padding of their slot
+            HistoryEntry::deserialize(&mut slot)
+                .map_err(|_| CalculatorError::InvalidAccountType.into())
+        })
+        .collect()
+    }
+}
+
+// The recorded slots of a history ring buffer with entries of `entry_len`
+// bytes, oldest first
+fn history_slots(
+    capacity: u32,
+    len: u32,
+    next: u32,
+    entry_len: usize,
+    history: &[u8],
+) -> Result<impl Iterator<Item = &[u8]>, ProgramError> {
+    let (capacity, len, next) = (capacity as usize, len as usize, next as usize);
+    if history.len() < capacity * entry_len {
+        msg!("Calculator account is too small for its history");
+        return Err(ProgramError::AccountDataTooSmall);
+    }
+
+    // The oldest entry is the one the buffer will overwrite next once full
+    let start = (next + capacity - len) % capacity.max(1);
+    Ok((0..len).map(move |i| {
+        let offset = (start + i) % capacity * entry_len;
+        &history[offset..offset + entry_len]
+    }))
+}
+
+impl From<&CalcResult> for PodCalcResult {
+    fn from(state: &CalcResult) -> Self {
+        Self {
+            discriminator: state.discriminator,
+            version: state.version,
+            is_initialized: state.is_initialized.into(),
+            authority: state.authority,
+            add_result: state.add_result.into(),
+            sub_result: state.sub_result.into(),
+            mul_result: state.mul_result.into(),
+            div_result: state.div_result.into(),
+            rem_result: state.rem_result.into(),
+            signed_add_result: state.signed_add_result.into(),
+            signed_sub_result: state.signed_sub_result.into(),
+            last_result: state.last_result.into(),
+            memory: state.memory.into(),
+            value: state.value.into(),
+            history_capacity: state.history_capacity.into(),
+            history_len: state.history_len.into(),
+            history_next: state.history_next.into(),
+            u64_result: state.u64_result.into(),
+            u128_result: state.u128_result.into(),
+            decimal_result: state.decimal_result.into(),
+        }
+    }
+}
+
+impl From<&PodCalcResult> for CalcResult {
+    fn from(state: &PodCalcResult) -> Self {
+        Self {
+            discriminator: state.discriminator,
+            version: state.version,
+            is_initialized: state.is_initialized.into(),
+            authority: state.authority,
+            add_result: state.add_result.into(),
+            sub_result: state.sub_result.into(),
+            mul_result: state.mul_result.into(),
+            div_result: state.div_result.into(),
+            rem_result: state.rem_result.into(),
+            signed_add_result: state.signed_add_result.into(),
+            signed_sub_result: state.signed_sub_result.into(),
+            last_result: state.last_result.into(),
+            memory: state.memory.into(),
+            value: state.value.into(),
+            history_capacity: state.history_capacity.into(),
+            history_len: state.history_len.into(),
+            history_next: state.history_next.into(),
+            u64_result: state.u64_result.into(),
+            u128_result: state.u128_result.into(),
+            decimal_result: state.decimal_result.into(),
+        }
+    }
+}
+
+/// State of the original calculator program, without a header
+#[derive(BorshSerialize, BorshDeserialize, Debug, Default, PartialEq, Eq)]
+pub struct CalcResultV1 {
+    /// Result of the addition operation
+    pub add_result: u32,
+    /// Result of the subtraction operation
+    pub sub_result: u32,
+}
+
+impl CalcResultV1 {
+    /// Size of the Borsh-encoded state in bytes, which was always the exact
+    /// size of version 1 accounts
+    pub const LEN: usize = 2 * 4;
+}
+
+/// Calculator state in any of the layouts the program has written
+#[derive(Debug, PartialEq, Eq)]
+pub enum VersionedCalcResult {
+    /// Version 1, the original layout with only the add and subtract results
+    V1(CalcResultV1),
+    /// Versions 2 to 6, header layouts that lack the fields later versions
+    /// appended, which are decoded as zero
+    Outdated(CalcResult),
+    /// The current layout
+    Current(CalcResult),
+}
+
+impl VersionedCalcResult {
+    /// Decode initialized calculator state of any version from account data,
+    /// rejecting uninitialized and foreign-typed accounts
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.len() == CalcResultV1::LEN {
+            let state = CalcResultV1::try_from_slice(data)?;
+            return Ok(Self::V1(state));
+        }
+
+        // Every later version only appended fields to the previous one, so
+        // decode the prefix it wrote with the remaining fields zeroed
+        let version = data.get(8).copied().unwrap_or_default();
+        let layout_len = CalcResult::layout_len(version).unwrap_or(CalcResult::LEN);
+        let prefix = data.get(..layout_len).ok_or_else(|| {
+            msg!("Account data is not calculator state");
+            CalculatorError::InvalidAccountType
+        })?;
+        let mut current = [0; CalcResult::LEN];
+        current[..layout_len].copy_from_slice(prefix);
+        let state = CalcResult::try_from_slice(&current).map_err(|_| {
+            msg!("Account data is not calculator state");
+            CalculatorError::InvalidAccountType
+        })?;
+
+        if !state.is_initialized {
+            msg!("Calculator account is not initialized");
+            return Err(CalculatorError::AccountNotInitialized.into());
+        }
+        if state.discriminator != CalcResult::DISCRIMINATOR {
+            msg!("Account discriminator does not match calculator state");
+            return Err(CalculatorError::InvalidAccountType.into());
+        }
+        if state.version == CalcResult::VERSION {
+            Ok(Self::Current(state))
+        } else if CalcResult::layout_len(state.version).is_some() {
+            Ok(Self::Outdated(state))
+        } else {
+            msg!("Unsupported calculator account version: {}", state.version);
+            Err(CalculatorError::UnsupportedAccountVersion.into())
+        }
+    }
+
+    /// Convert the state to the current layout, recording `authority` for
+    /// version 1 state, which had none
+    pub fn into_current(self, authority: Pubkey) -> CalcResult {
+        match self {
+            Self::V1(state) => CalcResult {
+                add_result: state.add_result,
+                sub_result: state.sub_result,
+                ..CalcResult::new(authority)
+            },
+            Self::Outdated(state) => CalcResult {
+                version: CalcResult::VERSION,
+                ..state
+            },
+            Self::Current(state) => state,
+        }
+    }
+
+    /// Decode the recorded history from the account data, oldest entry first,
+    /// converting entries recorded by older versions
+    pub fn history(&self, data: &[u8]) -> Result<Vec<HistoryEntry>, ProgramError> {
+        match self {
+            Self::Current(_) => decode_history(data),
+            Self::Outdated(state) if state.version >= 5 => {
+                let layout_len = CalcResult::layout_len(state.version).unwrap_or_default();
+                let entry_len = if state.version == 5 {
+                    HistoryEntryV5::LEN
+                } else {
+                    HistoryEntry::LEN
+                };
+                history_slots(
+                    state.history_capacity,
+                    state.history_len,
+                    state.history_next,
+                    entry_len,
+                    &data[layout_len..],
+                )?
+                .map(|mut slot| {
+                    if state.version == 5 {
+                        HistoryEntryV5::try_from_slice(slot)
+                            .map_err(|_| CalculatorError::InvalidAccountType.into())
+                            .and_then(HistoryEntryV5::into_current)
+                    } else {
+                        HistoryEntry::deserialize(&mut slot)
+                            .map_err(|_| CalculatorError::InvalidAccountType.into())
+                    }
+                })
+                .collect()
+            }
+            // Earlier versions had no history
+            _ => Ok(vec![]),
+        }
+    }
+}
+
+/// Decode the operation history of a calculator account from its data, oldest
+/// entry first, for use by clients
+pub fn decode_history(data: &[u8]) -> Result<Vec<HistoryEntry>, ProgramError> {
+    let (state, history) = PodCalcResult::load(data)?;
+    state.history(history)
+}
+
+/// A recorded calculator operation
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct HistoryEntry {
+    /// Slot the operation was executed in
+    pub slot: Slot,
+    /// Authority that signed the operation
+    pub signer: Pubkey,
+    /// Result the operation published
+    pub result: OperationResult,
+    /// The instruction, whose variant is the operation and whose fields are
+    /// its operands
+    pub instruction: CalculatorInstruction,
+}
+
+impl HistoryEntry {
+    /// Size of the history slot of an entry in bytes
+    ///
+    /// Each entry is Borsh-encoded at the start of its slot, followed by zero
+    /// padding, leaving 81 bytes for the result and the instruction.
+    pub const LEN: usize = 8 + 32 + 81;
+}
+
+// The longest result and instruction fit a history slot
+const _: () = assert!(
+    HistoryEntry::LEN >= 8 + 32 + OperationResult::MAX_LEN + CalculatorInstruction::MAX_LEN
+);
+
+// History entry recorded by version 5 accounts
+#[derive(BorshDeserialize)]
+struct HistoryEntryV5 {
+    opcode: u8,
+    operands: [i64; 2],
+    result: i64,
+    slot: Slot,
+    signer: Pubkey,
+}
+
+impl HistoryEntryV5 {
+    const LEN: usize = 1 + 2 * 8 + 8 + 8 + 32;
+
+    // Rebuild the instruction from its tag and the operands it had, which
+    // were the `u32` or `i64` operands of the arithmetic and accumulator
+    // instructions
+    fn into_current(self) -> Result<HistoryEntry, ProgramError> {
+        let [num1, num2] = self.operands;
+        let (operands, result) = match self.opcode {
+            0..=4 => (
+                [(num1 as u32).to_le_bytes(), (num2 as u32).to_le_bytes()].concat(),
+                OperationResult::Unsigned(self.result as u32),
+            ),
+            5 | 6 => (
+                [num1.to_le_bytes(), num2.to_le_bytes()].concat(),
+                OperationResult::Signed(self.result),
+            ),
+            15..=19 => (
+                num1.to_le_bytes().to_vec(),
+                OperationResult::Signed(self.result),
+            ),
+            _ => (vec![], OperationResult::Signed(self.result)),
+        };
+        let instruction = CalculatorInstruction::unpack(&[&[self.opcode][..], &operands].concat())?;
+
+        Ok(HistoryEntry {
+            slot: self.slot,
+            signer: self.signer,
+            result,
+            instruction,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{instruction::MulDivOperands, math::Rounding};
+
+    #[test]
+    fn test_find_calculator_address() {
+        let program_id = Pubkey::new_unique();
+        let user = Pubkey::new_unique();
+
+        let (address, bump) = find_calculator_address(&program_id, &user, "savings");
+        assert_eq!(
+            Pubkey::create_program_address(
+                &[CALCULATOR_SEED, user.as_ref(), b"savings", &[bump]],
+                &program_id
+            ),
+            Ok(address)
+        );
+
+        // Each user and label gets its own account
+        assert_ne!(find_calculator_address(&program_id, &user, "").0, address);
+        assert_ne!(
+            find_calculator_address(&program_id, &Pubkey::new_unique(), "savings").0,
+            address
+        );
+    }
+
+    #[test]
+    fn test_unpack_checks_header() {
+        let mut data = vec![0; CalcResult::LEN];
+
+        // A zeroed account is not initialized
+        assert_eq!(
+            CalcResult::unpack(&data),
+            Err(CalculatorError::AccountNotInitialized.into())
+        );
+        assert!(!CalcResult::is_initialized(&data));
+
+        let state = CalcResult::new(Pubkey::new_unique());
+        state.pack(&mut data).unwrap();
+        assert_eq!(CalcResult::unpack(&data).unwrap(), state);
+        assert!(CalcResult::is_initialized(&data));
+
+        // Foreign account types are rejected
+        let mut foreign = data.clone();
+        foreign[..8].copy_from_slice(b"notcalcs");
+        assert_eq!(
+            CalcResult::unpack(&foreign),
+            Err(CalculatorError::InvalidAccountType.into())
+        );
+
+        // Undecodable data is rejected
+        assert_eq!(
+            CalcResult::unpack(&data[..CalcResult::LEN - 1]),
+            Err(CalculatorError::InvalidAccountType.into())
+        );
+
+        // Layout versions this program does not understand are rejected
+        let mut future = data.clone();
+        future[8] = CalcResult::VERSION + 1;
+        assert_eq!(
+            CalcResult::unpack(&future),
+            Err(CalculatorError::UnsupportedAccountVersion.into())
+        );
+    }
+
+    #[test]
+    fn test_unpack_previous_versions() {
+        // Version 1 accounts hold just the two results
+        let v1 = [7u32.to_le_bytes(), 3u32.to_le_bytes()].concat();
+        let versioned = VersionedCalcResult::unpack(&v1).unwrap();
+        assert_eq!(
+            versioned,
+            VersionedCalcResult::V1(CalcResultV1 {
+                add_result: 7,
+                sub_result: 3
+            })
+        );
+        assert_eq!(
+            CalcResult::unpack(&v1),
+            Err(CalculatorError::UnsupportedAccountVersion.into())
+        );
+
+        let authority = Pubkey::new_unique();
+        let migrated = versioned.into_current(authority);
+        assert_eq!(
+            migrated,
+            CalcResult {
+                add_result: 7,
+                sub_result: 3,
+                ..CalcResult::new(authority)
+            }
+        );
+
+        // Version 2 added the header and the other operations' results
+        let v2 = [
+            &CalcResult::DISCRIMINATOR[..],
+            &[2, 1],
+            authority.as_ref(),
+            &[1u32, 2, 3, 4, 5].map(u32::to_le_bytes).concat(),
+            &[-6i64, 7].map(i64::to_le_bytes).concat(),
+        ]
+        .concat();
+        let versioned = VersionedCalcResult::unpack(&v2).unwrap();
+        let expected = CalcResult {
+            add_result: 1,
+            sub_result: 2,
+            mul_result: 3,
+            div_result: 4,
+            rem_result: 5,
+            signed_add_result: -6,
+            signed_sub_result: 7,
+            ..CalcResult::new(authority)
+        };
+        assert_eq!(
+            versioned,
+            VersionedCalcResult::Outdated(CalcResult {
+                version: 2,
+                ..expected
+            })
+        );
+        assert_eq!(
+            CalcResult::unpack(&v2),
+            Err(CalculatorError::UnsupportedAccountVersion.into())
+        );
+        assert_eq!(versioned.into_current(Pubkey::new_unique()), expected);
+
+        // Truncated headers are not calculator state
+        assert_eq!(
+            VersionedCalcResult::unpack(&v2[..v2.len() - 1]),
+            Err(CalculatorError::InvalidAccountType.into())
+        );
+    }
+
+    #[test]
+    fn test_pod_layout_matches_borsh() {
+        assert_eq!(std::mem::size_of::<PodCalcResult>(), CalcResult::LEN);
+        assert_eq!(std::mem::align_of::<PodCalcResult>(), 1);
+
+        let state = CalcResult {
+            add_result: 1,
+            sub_result: u32::MAX,
+            mul_result: 3,
+            div_result: 4,
+            rem_result: 5,
+            signed_add_result: i64::MIN,
+            signed_sub_result: -7,
+            last_result: 8,
+            memory: -9,
+            value: i64::MAX,
+            history_capacity: 11,
+            history_len: 12,
+            history_next: 13,
+            u64_result: u64::MAX - 14,
+            u128_result: u128::MAX - 15,
+            decimal_result: Decimal::new(16, 17),
+            ..CalcResult::new(Pubkey::new_unique())
+        };
+        let data = state.try_to_vec().unwrap();
+        let (view, history) = PodCalcResult::load(&data).unwrap();
+        assert_eq!(bytemuck::bytes_of(view), &data[..]);
+        assert_eq!(*view, PodCalcResult::from(&state));
+        assert_eq!(CalcResult::from(view), state);
+        assert!(history.is_empty());
+
+        // Fields written through the view are seen by the Borsh decoder
+        let mut data = data;
+        let (view, _) = PodCalcResult::load_mut(&mut data).unwrap();
+        view.memory = 42.into();
+        assert_eq!(CalcResult::unpack(&data).unwrap().memory, 42);
+
+        // The view rejects the same accounts as the decoder
+        let mut zeroed = vec![0; CalcResult::LEN];
+        assert_eq!(
+            PodCalcResult::load(&zeroed),
+            Err(CalculatorError::AccountNotInitialized.into())
+        );
+        assert_eq!(
+            PodCalcResult::load_mut(&mut zeroed[..8]),
+            Err(CalculatorError::UnsupportedAccountVersion.into())
+        );
+        assert_eq!(
+            PodCalcResult::load(&data[..CalcResult::LEN - 1]),
+            Err(CalculatorError::InvalidAccountType.into())
+        );
+        let mut invalid_flag = data.clone();
+        invalid_flag[9] = 2;
+        assert!(CalcResult::unpack(&invalid_flag).is_err());
+        assert_eq!(
+            PodCalcResult::load(&invalid_flag),
+            Err(CalculatorError::InvalidAccountType.into())
+        );
+    }
+
+    #[test]
+    fn test_history_ring_buffer() {
+        let signer = Pubkey::new_unique();
+        let mut data = vec![0; CalcResult::space(3)];
+        CalcResult {
+            history_capacity: 3,
+            ..CalcResult::new(signer)
+        }
+        .pack(&mut data)
+        .unwrap();
+        assert_eq!(decode_history(&data), Ok(vec![]));
+
+        // Entries of the longest result and instruction, filling their slots
+        // as far as they can
+        let entry = |slot: Slot| HistoryEntry {
+            slot,
+            signer,
+            result: OperationResult::Decimal(Decimal::new(u128::MAX - u128::from(slot), 38)),
+            instruction: CalculatorInstruction::MulDiv {
+                operands: MulDivOperands::U128 {
+                    num1: u128::MAX,
+                    num2: slot.into(),
+                    denominator: u128::MAX,
+                },
+                rounding: Rounding::Ceil,
+            },
+        };
+        let largest = entry(Slot::MAX);
+        assert_eq!(
+            largest.try_to_vec().unwrap().len(),
+            8 + 32 + OperationResult::MAX_LEN + CalculatorInstruction::MAX_LEN
+        );
+        let push = |data: &mut [u8], slot| {
+            let (state, history) = PodCalcResult::load_mut(data).unwrap();
+            state.push_history(history, &entry(slot)).unwrap();
+        };
+
+        // Partially filled
+        for slot in 1..=2 {
+            push(&mut data, slot);
+        }
+        assert_eq!(decode_history(&data), Ok(vec![entry(1), entry(2)]));
+
+        // Wrapped around, the oldest entries are overwritten
+        for slot in 3..=7 {
+            push(&mut data, slot);
+        }
+        assert_eq!(CalcResult::unpack(&data).unwrap().history_len, 3);
+        assert_eq!(
+            decode_history(&data),
+            Ok(vec![entry(5), entry(6), entry(7)])
+        );
+
+        // The data must hold the whole buffer
+        assert_eq!(
+            decode_history(&data[..CalcResult::space(3) - 1]),
+            Err(ProgramError::AccountDataTooSmall)
+        );
+
+        // Without a history nothing is recorded
+        let mut data = vec![0; CalcResult::LEN];
+        CalcResult::new(signer).pack(&mut data).unwrap();
+        push(&mut data, 1);
+        assert_eq!(decode_history(&data), Ok(vec![]));
+    }
+}
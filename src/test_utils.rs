@@ -0,0 +1,191 @@
+//! Shared helpers for running the processor natively in unit tests
+
+use crate::processor::handle_instruction;
+use solana_program::{
+    account_info::AccountInfo,
+    clock::{Clock, Epoch, Slot},
+    entrypoint::{ProgramResult, MAX_PERMITTED_DATA_INCREASE, SUCCESS},
+    instruction::Instruction,
+    program_error::ProgramError,
+    program_stubs,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction::SystemInstruction,
+    system_program,
+};
+use std::{cell::RefCell, slice};
+
+/// Program id the calculator runs under in tests that use cross-program
+/// invocations
+pub const CPI_PROGRAM_ID: Pubkey = Pubkey::new_from_array([7; 32]);
+
+/// Program id of the program invoking the calculator in CPI tests
+pub const CALLER_PROGRAM_ID: Pubkey = Pubkey::new_from_array([8; 32]);
+
+/// Slot reported by the clock sysvar in tests
+pub const TEST_SLOT: Slot = 42;
+
+thread_local! {
+    // Return data set by the program, kept per test thread
+    static RETURN_DATA: RefCell<Option<(Pubkey, Vec<u8>)>> = const { RefCell::new(None) };
+}
+
+/// Stand-ins for the runtime syscalls used by the program
+pub struct TestSyscallStubs;
+
+impl program_stubs::SyscallStubs for TestSyscallStubs {
+    fn sol_set_return_data(&self, data: &[u8]) {
+        RETURN_DATA.with(|cell| *cell.borrow_mut() = Some((CPI_PROGRAM_ID, data.to_vec())));
+    }
+
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        RETURN_DATA.with(|cell| cell.borrow().clone())
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { *(var_addr as *mut Rent) = Rent::default() };
+        SUCCESS
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = Clock {
+            slot: TEST_SLOT,
+            ..Clock::default()
+        };
+        unsafe { *(var_addr as *mut Clock) = clock };
+        SUCCESS
+    }
+
+    // Run calculator instructions and emulate the system program, checking
+    // signatures the way the runtime would
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        // Only the calculator invokes the system program; everything else
+        // invoking the calculator is the test caller program
+        let caller_id = if system_program::check_id(&instruction.program_id) {
+            CPI_PROGRAM_ID
+        } else {
+            CALLER_PROGRAM_ID
+        };
+        let account = |index: usize| {
+            let meta = &instruction.accounts[index];
+            let mut info = account_infos
+                .iter()
+                .find(|info| *info.key == meta.pubkey)
+                .unwrap()
+                .clone();
+            let signed = info.is_signer
+                || signers_seeds.iter().any(|seeds| {
+                    Pubkey::create_program_address(seeds, &caller_id) == Ok(meta.pubkey)
+                });
+            if meta.is_signer && !signed {
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+            info.is_signer = meta.is_signer;
+            info.is_writable = meta.is_writable;
+            Ok(info)
+        };
+
+        if instruction.program_id == CPI_PROGRAM_ID {
+            let callee_accounts = (0..instruction.accounts.len())
+                .map(account)
+                .collect::<Result<Vec<_>, _>>()?;
+            RETURN_DATA.with(|cell| *cell.borrow_mut() = None);
+            return handle_instruction(&CPI_PROGRAM_ID, &callee_accounts, &instruction.data);
+        }
+
+        assert!(system_program::check_id(&instruction.program_id));
+        match bincode::deserialize(&instruction.data).unwrap() {
+            SystemInstruction::CreateAccount {
+                lamports,
+                space,
+                owner,
+            } => {
+                let (from, to) = (account(0)?, account(1)?);
+                assert_eq!(to.lamports(), 0);
+                assert_eq!(to.data_len() as u64, space);
+                **from.lamports.borrow_mut() -= lamports;
+                **to.lamports.borrow_mut() += lamports;
+                to.assign(&owner);
+            }
+            SystemInstruction::Transfer { lamports } => {
+                let (from, to) = (account(0)?, account(1)?);
+                **from.lamports.borrow_mut() -= lamports;
+                **to.lamports.borrow_mut() += lamports;
+            }
+            SystemInstruction::Allocate { space } => {
+                assert_eq!(account(0)?.data_len() as u64, space);
+            }
+            SystemInstruction::Assign { owner } => account(0)?.assign(&owner),
+            other => panic!("unexpected system instruction {:?}", other),
+        }
+        Ok(())
+    }
+}
+
+/// Owned backing storage for an `AccountInfo`
+///
+/// Mirrors the runtime's serialization closely enough for
+/// `AccountInfo::realloc`: the original data length sits right before the key,
+/// and the data is preceded by its current length and followed by room to grow.
+#[repr(C)]
+pub struct TestAccount {
+    original_data_len: u32,
+    pub key: Pubkey,
+    pub lamports: u64,
+    pub owner: Pubkey,
+    // The data length followed by the data, in words to keep it aligned
+    buffer: Vec<u64>,
+}
+
+impl TestAccount {
+    pub fn new(data_len: usize, owner: &Pubkey) -> Self {
+        let words = 1 + (data_len + MAX_PERMITTED_DATA_INCREASE + 7) / 8;
+        let mut buffer = vec![0; words];
+        buffer[0] = data_len as u64;
+        Self {
+            original_data_len: data_len as u32,
+            key: Pubkey::new_unique(),
+            lamports: 0,
+            owner: *owner,
+            buffer,
+        }
+    }
+
+    pub fn data(&self) -> &[u8] {
+        let len = self.buffer[0] as usize;
+        let bytes = unsafe {
+            slice::from_raw_parts(self.buffer.as_ptr() as *const u8, self.buffer.len() * 8)
+        };
+        &bytes[8..8 + len]
+    }
+
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        let len = self.buffer[0] as usize;
+        let bytes = unsafe {
+            slice::from_raw_parts_mut(self.buffer.as_mut_ptr() as *mut u8, self.buffer.len() * 8)
+        };
+        &mut bytes[8..8 + len]
+    }
+
+    pub fn info(&mut self, is_signer: bool, is_writable: bool) -> AccountInfo<'_> {
+        let len = self.buffer[0] as usize;
+        let bytes = unsafe {
+            slice::from_raw_parts_mut(self.buffer.as_mut_ptr() as *mut u8, self.buffer.len() * 8)
+        };
+        AccountInfo::new(
+            &self.key,
+            is_signer,
+            is_writable,
+            &mut self.lamports,
+            &mut bytes[8..8 + len],
+            &self.owner,
+            false,
+            Epoch::default(),
+        )
+    }
+}
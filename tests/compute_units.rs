@@ -0,0 +1,135 @@
+//! Compute units consumed by the SBF program, run with `cargo test-sbf`
+//!
+//! `test_powers_and_roots_stay_within_budget` holds `Pow`, `Isqrt` and
+//! `NthRoot` to the compute-unit budgets documented on their instructions.
+//!
+//! `test_zero_copy_state_update_saves_compute_units` compares against a
+//! baseline program built to update state through Borsh, which must be built
+//! first:
+//!
+//! ```sh
+//! cargo build-sbf --features borsh-state-baseline --sbf-out-dir target/deploy/baseline
+//! mv target/deploy/baseline/calculator.so target/deploy/calculator_borsh_state.so
+//! cargo test-sbf
+//! ```
+
+#![cfg(feature = "test-sbf")]
+
+use calculator::{
+    instruction::{self, Operand},
+    state::find_calculator_address,
+};
+use solana_program_test::{tokio, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    instruction::Instruction, pubkey::Pubkey, signature::Signer, transaction::Transaction,
+};
+
+// Compute-unit budgets of the bounded-cost operations, as documented on their
+// instructions
+const POW_BUDGET: u64 = 20_000;
+const ISQRT_BUDGET: u64 = 20_000;
+const NTH_ROOT_BUDGET: u64 = 50_000;
+
+// Run `instruction` in its own transaction, returning the compute units it
+// consumed
+async fn compute_units(context: &mut ProgramTestContext, instruction: Instruction) -> u64 {
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.last_blockhash,
+    );
+    let outcome = context
+        .banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    outcome.result.unwrap();
+    outcome.metadata.unwrap().compute_units_consumed
+}
+
+// Create the payer's calculator account, recording history like most
+// accounts do
+async fn create_calculator(context: &mut ProgramTestContext, program_id: &Pubkey) -> Pubkey {
+    let payer = context.payer.pubkey();
+    let create = instruction::create_calculator(program_id, &payer, "", 8);
+    compute_units(context, create).await;
+    find_calculator_address(program_id, &payer, "").0
+}
+
+#[tokio::test]
+async fn test_zero_copy_state_update_saves_compute_units() {
+    let zero_copy_id = Pubkey::new_unique();
+    let borsh_id = Pubkey::new_unique();
+    let mut program_test = ProgramTest::new("calculator", zero_copy_id, None);
+    program_test.add_program("calculator_borsh_state", borsh_id, None);
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.pubkey();
+
+    let mut units = vec![];
+    for program_id in [zero_copy_id, borsh_id] {
+        let calculator = create_calculator(&mut context, &program_id).await;
+        let add = instruction::add(&program_id, &calculator, &payer, 1, 2);
+        units.push(compute_units(&mut context, add).await);
+    }
+    let (zero_copy, borsh) = (units[0], units[1]);
+    assert!(
+        zero_copy < borsh,
+        "zero-copy update consumed {} compute units, Borsh {}",
+        zero_copy,
+        borsh
+    );
+}
+
+#[tokio::test]
+async fn test_powers_and_roots_stay_within_budget() {
+    let program_id = Pubkey::new_unique();
+    let mut context = ProgramTest::new("calculator", program_id, None)
+        .start_with_context()
+        .await;
+    let payer = context.payer.pubkey();
+    let calculator = create_calculator(&mut context, &program_id).await;
+
+    let pow = |base, exponent| instruction::pow(&program_id, &calculator, &payer, base, exponent);
+    let isqrt = |value| instruction::isqrt(&program_id, &calculator, &payer, value);
+    let nth_root =
+        |value, degree| instruction::nth_root(&program_id, &calculator, &payer, value, degree);
+    let cases = [
+        ("Pow", pow(Operand::U128(1), 127), POW_BUDGET),
+        ("Pow", pow(Operand::U128(1), u32::MAX), POW_BUDGET),
+        ("Pow", pow(Operand::U64(1), 63), POW_BUDGET),
+        ("Isqrt", isqrt(Operand::U128(u128::MAX)), ISQRT_BUDGET),
+        (
+            "NthRoot",
+            nth_root(Operand::U128(u128::MAX), 2),
+            NTH_ROOT_BUDGET,
+        ),
+        (
+            "NthRoot",
+            nth_root(Operand::U128(u128::MAX), 3),
+            NTH_ROOT_BUDGET,
+        ),
+        (
+            "NthRoot",
+            nth_root(Operand::U64(u64::MAX), 3),
+            NTH_ROOT_BUDGET,
+        ),
+        (
+            "NthRoot",
+            nth_root(Operand::U128(u128::MAX), u32::MAX),
+            NTH_ROOT_BUDGET,
+        ),
+    ];
+    for (name, instruction, budget) in cases {
+        let data = instruction.data.clone();
+        let units = compute_units(&mut context, instruction).await;
+        assert!(
+            units <= budget,
+            "{} {:?} consumed {} compute units, over its budget of {}",
+            name,
+            data,
+            units,
+            budget
+        );
+    }
+}